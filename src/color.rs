@@ -0,0 +1,139 @@
+// //// Color //// //
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use super::error::{LgError, Result};
+
+// Each kind is drawn in its own ANSI color: the one set under `colors`
+// in the config, else one picked from a hash of its name so it stays
+// the same from run to run. Nil and unkinded time stay uncolored.
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+	// Only when stdout is a terminal and `NO_COLOR` isn't set.
+	Auto,
+	Always,
+	Never,
+}
+
+impl ColorMode {
+	pub fn parse(name: &str) -> Result<ColorMode> {
+		match name {
+			"auto" => Ok(ColorMode::Auto),
+			"always" => Ok(ColorMode::Always),
+			"never" => Ok(ColorMode::Never),
+			_ => Err(LgError::Usage("lg --color=auto|always|never ...".to_string())),
+		}
+	}
+}
+
+const NAMES: [&str; 16] = [
+	"black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+	"bright-black", "bright-red", "bright-green", "bright-yellow",
+	"bright-blue", "bright-magenta", "bright-cyan", "bright-white",
+];
+
+// Readable on dark and light terminals alike.
+const PALETTE: [u8; 12] = [1, 2, 3, 4, 5, 6, 9, 10, 11, 12, 13, 14];
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static COLORS: RwLock<BTreeMap<String, u8>> = RwLock::new(BTreeMap::new());
+
+fn parse_color(name: &str) -> Option<u8> {
+	// A name from `NAMES`, or a number from the 256-color table.
+	NAMES.iter()
+		.position(|known| *known == name.to_lowercase())
+		.map(|index| index as u8)
+		.or_else(|| name.parse::<u8>().ok())
+}
+
+pub fn set_color(mode: ColorMode, colors: &BTreeMap<String, String>) -> Result<()> {
+	let mut parsed = BTreeMap::new();
+	for (kind, name) in colors {
+		let color = parse_color(name).ok_or_else(|| LgError::Invalid(
+			format!("Config color \"{}\" for {} is invalid.", name, kind),
+			format!("Must be one of {}, or 0-255.", NAMES.join(", "))))?;
+		parsed.insert(kind.to_string(), color);
+	}
+	*COLORS.write().unwrap() = parsed;
+	ENABLED.store(match mode {
+		ColorMode::Auto => std::io::stdout().is_terminal() & std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+		ColorMode::Always => true,
+		ColorMode::Never => false,
+	}, Ordering::Relaxed);
+	Ok(())
+}
+
+pub fn enabled() -> bool {
+	ENABLED.load(Ordering::Relaxed)
+}
+
+fn kind_color(kind: &str) -> Option<u8> {
+	if kind.is_empty() | (kind == "∅") {
+		return None;
+	}
+	if let Some(color) = COLORS.read().unwrap().get(kind) {
+		return Some(*color);
+	}
+	// FNV-1a; std's hasher isn't promised to stay the same across releases.
+	let hash = kind.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3));
+	Some(PALETTE[(hash % PALETTE.len() as u64) as usize])
+}
+
+pub fn paint(text: &str, kind: &str) -> String {
+	match kind_color(kind).filter(|_| enabled()) {
+		Some(color @ 0..=7) => format!("\x1b[{}m{}\x1b[0m", 30 + color, text),
+		Some(color @ 8..=15) => format!("\x1b[{}m{}\x1b[0m", 90 + color - 8, text),
+		Some(color) => format!("\x1b[38;5;{}m{}\x1b[0m", color, text),
+		None => text.to_string(),
+	}
+}
+
+pub fn bold(text: &str) -> String {
+	match enabled() {
+		true => format!("\x1b[1m{}\x1b[0m", text),
+		false => text.to_string(),
+	}
+}
+
+pub fn paint_rows(rows: &[String], width: usize, runs: &[(usize, &str)]) -> Vec<String> {
+	// Color each row's columns by the kind whose run covers them. `runs`
+	// are the columns each entry starts at, counted on across rows.
+	use unicode_segmentation::UnicodeSegmentation;
+	if !enabled() {
+		return rows.to_vec();
+	}
+	rows.iter()
+		.enumerate()
+		.map(|(index, row)| {
+			let mut painted = String::new();
+			let mut run = (String::new(), "");
+			let mut column = index * width;
+			for glyph in row.graphemes(true) {
+				let kind = runs.iter()
+					.rev()
+					.find(|(start, _)| *start <= column)
+					.map_or("", |(_, kind)| *kind);
+				if kind != run.1 {
+					painted.push_str(&paint(&run.0, run.1));
+					run = (String::new(), kind);
+				}
+				run.0.push_str(glyph);
+				column += super::glyph_width(glyph);
+			}
+			painted.push_str(&paint(&run.0, run.1));
+			painted
+		})
+		.collect()
+}
+
+pub fn legend(kinds: &[&str]) -> String {
+	// `▌Work ▌Meal ...` in the kinds' colors, nil left out.
+	kinds.iter()
+		.filter(|kind| kind_color(kind).is_some())
+		.map(|kind| paint(&format!("▌{}", kind), kind))
+		.collect::<Vec<_>>()
+		.join(" ")
+}
@@ -0,0 +1,112 @@
+// //// Completions //// //
+use chrono::prelude::*;
+use super::log::Log;
+use super::kinds::KindRules;
+use super::time::format_time;
+
+// The scripts hold no word lists of their own; each asks
+// `lg complete -- WORDS...` for the candidates at the cursor.
+
+const BASH: &str = r#"_lg() {
+	local line=${COMP_LINE:0:COMP_POINT} words
+	read -ra words <<< "$line"
+	[[ $line == *' ' || ${#words[@]} -eq 0 ]] && words+=('')
+	local cur=${words[-1]}
+	local IFS=$'\n'
+	COMPREPLY=($(lg complete -- "${words[@]:1}" 2>/dev/null))
+	# Bash splits words at `:`; replace only the part after it.
+	if [[ $cur == *:* && $COMP_WORDBREAKS == *:* ]]; then
+		COMPREPLY=("${COMPREPLY[@]#"${cur%:*}:"}")
+	fi
+}
+complete -o nosort -F _lg lg
+"#;
+
+const ZSH: &str = r#"#compdef lg
+_lg() {
+	local -a candidates
+	candidates=(${(f)"$(lg complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)"})
+	compadd -V lg -Q -- "${candidates[@]}"
+}
+compdef _lg lg
+"#;
+
+const FISH: &str = r#"function __lg_complete
+	set -l words (commandline -opc)
+	lg complete -- $words[2..-1] (commandline -ct | string collect --allow-empty) 2>/dev/null
+end
+complete -c lg -f -a '(__lg_complete)'
+"#;
+
+pub fn script(shell: &str) -> Option<&'static str> {
+	match shell {
+		"bash" => Some(BASH),
+		"zsh" => Some(ZSH),
+		"fish" => Some(FISH),
+		_ => None,
+	}
+}
+
+pub fn kinds(log: &Log, rules: &KindRules) -> Vec<String> {
+	// Kinds in the log, most used first, then those only the rules know.
+	let mut counts = Vec::<(&str, usize)>::new();
+	for entry in log.iter().filter(|e| !e.kind.is_empty() && !e.is_nil()) {
+		match counts.iter_mut().find(|(kind, _)| *kind == entry.kind) {
+			Some((_, n)) => *n += 1,
+			None => counts.push((&entry.kind, 1)),
+		}
+	}
+	counts.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+	let mut kinds = counts.into_iter()
+		.map(|(kind, _)| kind.to_string())
+		.collect::<Vec<_>>();
+	for kind in rules.known() {
+		if !kinds.contains(&kind) {
+			kinds.push(kind);
+		}
+	}
+	kinds
+}
+
+pub fn tasks(log: &Log, limit: usize) -> Vec<String> {
+	// The latest distinct task names, newest first.
+	let mut tasks = Vec::<String>::new();
+	for entry in log.vec.iter().rev().filter(|e| !e.data.is_empty()) {
+		if tasks.len() >= limit {
+			break;
+		}
+		if !tasks.contains(&entry.data) {
+			tasks.push(entry.data.clone());
+		}
+	}
+	tasks
+}
+
+pub fn boundaries(log: &Log, now: DateTime<FixedOffset>) -> Vec<String> {
+	// Now, when the current task started, and when the one before it did.
+	let task = log.task_at(now);
+	let mut times = vec![now];
+	if !task.is_empty() {
+		times.push(task.time);
+		times.extend(log.predecessor(task).map(|pred| pred.time));
+	}
+	let mut times = times.into_iter()
+		.map(format_time)
+		.collect::<Vec<_>>();
+	times.dedup();
+	times
+}
+
+pub fn candidates(word: &str, log: &Log, rules: &KindRules, now: DateTime<FixedOffset>) -> Vec<String> {
+	// What the terse grammar could take in place of `word`.
+	let (prefix, candidates) = match word.chars().next() {
+		Some(':') => (":", kinds(log, rules)),
+		Some('@') => ("@", boundaries(log, now).into_iter().rev().collect()),
+		Some('-') => ("-", boundaries(log, now)),
+		_ => ("", tasks(log, 50)),
+	};
+	candidates.into_iter()
+		.map(|candidate| format!("{}{}", prefix, candidate))
+		.filter(|candidate| candidate.starts_with(word))
+		.collect()
+}
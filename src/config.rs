@@ -0,0 +1,95 @@
+// //// Config //// //
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use super::color::ColorMode;
+use super::store::Backend;
+use super::kinds::KindRules;
+use super::time::{HourFormat, Window};
+use super::error::{LgError, Result};
+
+// User settings, kept in YAML beside the other XDG files.
+// Every field is optional so an absent or partial file means defaults.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+	// Profile used when `--profile` isn't given.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub profile: Option<String>,
+	// How many rotated copies of the log to keep (`log.yml.1` is newest).
+	pub backups: usize,
+	// Seconds to wait for another `lg` to release the log.
+	pub lock_timeout: u64,
+	// Backend for the default log file, `log.yml` or `log.sqlite`.
+	pub store: Backend,
+	// Rules for inferring `:Kind` from a task name.
+	pub kinds: KindRules,
+	// IANA zone name, e.g. `Europe/Berlin`, used when `TZ` isn't set.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub timezone: Option<String>,
+	// Print times as `24h` (14:05) or `12h` (2:05pm).
+	pub hour_format: HourFormat,
+	// Which day a bare `@9:00` means.
+	pub window: Window,
+	// Rows of the bare `lg` view: `15m`, `30m`, `1h`, `2h` or `1d`.
+	pub period: String,
+	// Draw the timeline in color: `auto`, `always` or `never`.
+	pub color: ColorMode,
+	// Colors for kinds, e.g. `Work: blue` or `Sleep: 60`, overriding the picked ones.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	pub colors: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			profile: None,
+			backups: 5,
+			lock_timeout: 10,
+			store: Backend::Yaml,
+			kinds: KindRules::default(),
+			timezone: None,
+			hour_format: HourFormat::H24,
+			window: Window::default(),
+			period: "1h".to_string(),
+			color: ColorMode::Auto,
+			colors: BTreeMap::new(),
+		}
+	}
+}
+
+impl Config {
+	pub fn path() -> PathBuf {
+		// `LG_CONFIG` if set, else `$XDG_CONFIG_HOME/lg/config.yml`.
+		if let Some(path) = env::var_os("LG_CONFIG").filter(|path| !path.is_empty()) {
+			return PathBuf::from(path);
+		}
+		let config_home = match env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()) {
+			Some(path) => PathBuf::from(path),
+			None => match env::var_os("HOME") {
+				Some(home) => Path::new(&home).join(".config"),
+				None => PathBuf::from("."),
+			},
+		};
+		config_home.join("lg").join("config.yml")
+	}
+
+	pub fn load(path: &Path) -> Result<Config> {
+		let file = match std::fs::File::open(path) {
+			Ok(file) => file,
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+			Err(err) => return Err(LgError::Io("Config file could not be opened.".to_string(), err)),
+		};
+		serde_yaml::from_reader(file)
+			.map_err(|err| LgError::Invalid("Config file is invalid.".to_string(), err.to_string()))
+	}
+
+	pub fn save(&self, path: &Path) -> Result<()> {
+		if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+			std::fs::create_dir_all(dir).map_err(LgError::io("Config directory could not be created."))?;
+		}
+		let file = std::fs::File::create(path).map_err(LgError::io("Config file could not be created."))?;
+		serde_yaml::to_writer(file, self)
+			.map_err(|err| LgError::Invalid("Config serialization failed.".to_string(), err.to_string()))
+	}
+}
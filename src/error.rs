@@ -0,0 +1,110 @@
+// //// Error //// //
+use std::fmt;
+use chrono::prelude::*;
+
+// Everything that can go wrong, from a mistyped flag to an unreadable file.
+#[derive(Debug)]
+pub enum LgError {
+	// The arguments don't form a command.
+	Usage(String),
+	Arguments(String),
+	DuplicateFlag(String),
+	InvalidTime(String),
+	InvalidDuration(String),
+	InvalidRange(String),
+	UnknownKind(String, Vec<String>),
+	MissingData,
+	MissingKind(String),
+	NoteWithoutData,
+	// The command doesn't fit the log.
+	NoEntry(DateTime<FixedOffset>),
+	NothingToRetcon,
+	Conflict(String),
+	Refused(String),
+	NoProfile(String, Vec<String>),
+	NoActiveProfile(Vec<String>),
+	ProfileExists(String),
+	// The files behind the log.
+	Locked(String),
+	Io(String, std::io::Error),
+	Invalid(String, String),
+	Database(rusqlite::Error),
+}
+
+pub type Result<T> = std::result::Result<T, LgError>;
+
+impl LgError {
+	pub fn exit_code(&self) -> i32 {
+		// 2 for a command that couldn't be understood, 3 for a busy
+		// log, and 1 for anything else that stopped it.
+		match self {
+			LgError::Usage(_)
+			| LgError::Arguments(_)
+			| LgError::DuplicateFlag(_)
+			| LgError::InvalidTime(_)
+			| LgError::InvalidDuration(_)
+			| LgError::InvalidRange(_)
+			| LgError::UnknownKind(..)
+			| LgError::MissingData
+			| LgError::MissingKind(_)
+			| LgError::NoteWithoutData => 2,
+			LgError::Locked(_) => 3,
+			_ => 1,
+		}
+	}
+
+	pub fn io(context: &str) -> impl FnOnce(std::io::Error) -> LgError + '_ {
+		// For `map_err`: `File::open(path).map_err(LgError::io("Log file could not be opened."))`
+		move |err| LgError::Io(context.to_string(), err)
+	}
+}
+
+impl fmt::Display for LgError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			LgError::Usage(usage) => write!(f, "Usage: {}", usage),
+			LgError::Arguments(msg) => write!(f, "{}", msg),
+			LgError::DuplicateFlag(flag) => write!(f, "\"{}\" flag already used!", flag),
+			LgError::InvalidTime(time) => write!(f, "Invalid timestamp \"{}\"--must be a time as `13:00`, `1:`, `:30`, `3pm` or `noon`, within 0:00-23:59, optionally after a date as `yesterday`, `mon`, `-2d`, `08-10`, `Aug10` or `2019-08-10`; or a span ago as `-15m`.", time),
+			LgError::InvalidDuration(span) => write!(f, "Invalid duration \"{}\"--must be of `1:30`, `:30`, or `30` format, or in units as `1h30m`, `90m`, `1.5h` or `2d`.", span),
+			LgError::InvalidRange(range) => write!(f, "Invalid range \"{}\"--must be a count as `24`, a date as `mon` or `Feb4`, dates as `Feb4-Feb10`, or times as `14:37-15:00`, `:37-:00` or `14-16`.", range),
+			LgError::UnknownKind(data, known) => write!(f,
+				"Entry is of unknown kind! Give one with `:Kind` or add a rule with `lg kinds add`. {}\nKnown kinds: {}",
+				data, known.join(", ")),
+			LgError::MissingData => write!(f, "No data provided."),
+			LgError::MissingKind(data) => write!(f, "No kind provided. {}", data),
+			LgError::NoteWithoutData => write!(f, "Notes provided but no kind nor data."),
+			LgError::NoEntry(time) => write!(f, "No entry starts at {}.", super::time::format_datetime(*time)),
+			LgError::NothingToRetcon => write!(f, "No available (not nil) task to retcon!"),
+			LgError::Conflict(msg) => write!(f, "{}", msg),
+			LgError::Refused(msg) => write!(f, "{}", msg),
+			LgError::NoProfile(name, known) => write!(f,
+				"No such profile! {} Create it with `lg profiles create`.\nProfiles: {}",
+				name, known.join(", ")),
+			LgError::NoActiveProfile(known) => write!(f,
+				"Several profiles and none chosen! Pick one with `--profile` or `lg profiles default`.\nProfiles: {}",
+				known.join(", ")),
+			LgError::ProfileExists(name) => write!(f, "Profile already exists! {}", name),
+			LgError::Locked(msg) => write!(f, "{}", msg),
+			LgError::Io(context, err) => write!(f, "{} {}", context, err),
+			LgError::Invalid(context, err) => write!(f, "{} {}", context, err),
+			LgError::Database(err) => write!(f, "Log database error. {}", err),
+		}
+	}
+}
+
+impl std::error::Error for LgError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			LgError::Io(_, err) => Some(err),
+			LgError::Database(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<rusqlite::Error> for LgError {
+	fn from(err: rusqlite::Error) -> Self {
+		LgError::Database(err)
+	}
+}
@@ -0,0 +1,102 @@
+// //// Format //// //
+use chrono::Duration;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use super::error::{LgError, Result};
+
+// What the views, reports and the retcon summary print: text for
+// people, or records for scripts as one JSON array, one JSON object a
+// line (`jsonl`), or CSV under a header row.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+	Text,
+	Json,
+	Jsonl,
+	Csv,
+}
+
+impl Format {
+	pub fn parse(name: &str) -> Result<Format> {
+		match name {
+			"text" => Ok(Format::Text),
+			"json" => Ok(Format::Json),
+			"jsonl" => Ok(Format::Jsonl),
+			"csv" => Ok(Format::Csv),
+			_ => Err(LgError::Usage("lg --format text|json|jsonl|csv ...".to_string())),
+		}
+	}
+}
+
+// Something printed as records. Records are flat, so that each field is
+// a CSV column.
+pub trait Record: Serialize {
+	// Its fields in order: the CSV header, even over no records.
+	const COLUMNS: &'static [&'static str];
+}
+
+pub fn seconds<S: Serializer>(duration: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+	// Durations are whole seconds.
+	serializer.serialize_i64(duration.num_seconds())
+}
+
+fn csv_field(value: &Value) -> String {
+	// Quoted only when it holds a comma, quote or line break.
+	let field = match value {
+		Value::Null => String::new(),
+		Value::String(text) => text.to_string(),
+		value => value.to_string(),
+	};
+	match field.contains([',', '"', '\n', '\r']) {
+		true => format!("\"{}\"", field.replace('"', "\"\"")),
+		false => field,
+	}
+}
+
+pub fn print<T: Record>(format: Format, records: &[T]) {
+	let values = records.iter()
+		.map(|record| serde_json::to_value(record).expect("Records always serialize!"))
+		.collect::<Vec<_>>();
+	match format {
+		Format::Text | Format::Json => println!("{}", serde_json::to_string_pretty(&values).expect("Records always serialize!")),
+		Format::Jsonl => for value in &values {
+			println!("{}", value);
+		},
+		Format::Csv => {
+			println!("{}", T::COLUMNS.join(","));
+			for value in &values {
+				println!("{}", T::COLUMNS.iter()
+					.map(|column| csv_field(&value[column]))
+					.collect::<Vec<_>>()
+					.join(","));
+			}
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use chrono::prelude::*;
+	use crate::list::Item;
+	use crate::log::LogEntry;
+	use crate::report::Row;
+	use crate::tests::at;
+
+	fn keys<T: Record>(record: &T) -> Vec<String> {
+		match serde_json::to_value(record).unwrap() {
+			Value::Object(fields) => fields.keys().cloned().collect(),
+			_ => Vec::new(),
+		}
+	}
+
+	#[test]
+	fn columns_are_the_fields_in_order() {
+		let time: DateTime<FixedOffset> = at("2026-10-18T09:00:00-07:00");
+		let entry = LogEntry::new(time, "Work", "Coding", "");
+		let item = Item::new(&entry, None, time);
+		assert_eq!(keys(&item), Item::COLUMNS);
+		let row = Row { start: time, end: time, kind: None, part: None, duration: Duration::zero(), share: None };
+		assert_eq!(keys(&row), Row::COLUMNS);
+	}
+}
@@ -0,0 +1,164 @@
+// //// Journal //// //
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use chrono::prelude::*;
+use super::log::LogEntry;
+use super::error::{LgError, Result};
+
+// An append-only record of every change made to the log, one JSON
+// object per line beside the log file. Undo and redo are themselves
+// appended, so replaying the file top to bottom rebuilds the undo stack.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+	Do,
+	Undo(usize),
+	Redo(usize),
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Record {
+	pub seq: usize,
+	pub time: DateTime<FixedOffset>,
+	pub profile: String,
+	pub argv: Vec<String>,
+	pub action: Action,
+	// Entries as they were, and as they became.
+	pub before: Vec<LogEntry>,
+	pub after: Vec<LogEntry>,
+}
+
+pub struct Journal {
+	path: PathBuf,
+	pub records: Vec<Record>,
+	// Numbers of the lines that couldn't be read, e.g. torn by a crash.
+	pub skipped: Vec<usize>,
+}
+
+impl Journal {
+	pub fn path(log_path: &Path) -> PathBuf {
+		let mut path = log_path.as_os_str().to_owned();
+		path.push(".journal");
+		PathBuf::from(path)
+	}
+
+	pub fn load(log_path: &Path) -> Result<Journal> {
+		let path = Journal::path(log_path);
+		let file = match std::fs::File::open(&path) {
+			Ok(file) => file,
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound =>
+				return Ok(Journal { path, records: Vec::new(), skipped: Vec::new() }),
+			Err(err) => return Err(LgError::Io("Journal could not be opened.".to_string(), err)),
+		};
+		let mut records = Vec::new();
+		let mut skipped = Vec::new();
+		for (n, line) in std::io::BufReader::new(file).lines().enumerate() {
+			let line = line.map_err(LgError::io("Journal could not be read."))?;
+			// A line torn by an interrupted append is passed over, and
+			// the records after it still count.
+			match serde_json::from_str::<Record>(&line) {
+				Ok(record) => records.push(record),
+				Err(_) if line.trim().is_empty() => (),
+				Err(_) => skipped.push(n + 1),
+			}
+		}
+		Ok(Journal { path, records, skipped })
+	}
+
+	pub fn append(&mut self, profile: &str, argv: Vec<String>, action: Action, before: Vec<LogEntry>, after: Vec<LogEntry>) -> Result<&Record> {
+		let record = Record {
+			seq: self.records.last().map_or(1, |r| r.seq + 1),
+			time: super::time::now(),
+			profile: profile.to_string(),
+			argv, action, before, after,
+		};
+		let mut line = serde_json::to_string(&record)
+			.map_err(|err| LgError::Invalid("Journal serialization failed.".to_string(), err.to_string()))?;
+		line.push('\n');
+		let mut file = std::fs::OpenOptions::new()
+			.create(true)
+			.read(true)
+			.append(true)
+			.open(&self.path)
+			.map_err(LgError::io("Journal could not be opened."))?;
+		// Start on a line of its own after a torn one.
+		let mut last = [b'\n'];
+		if file.seek(SeekFrom::End(0)).map_err(LgError::io("Journal could not be read."))? > 0 {
+			file.seek(SeekFrom::End(-1))
+				.and_then(|_| file.read_exact(&mut last))
+				.map_err(LgError::io("Journal could not be read."))?;
+		}
+		if last[0] != b'\n' {
+			line.insert(0, '\n');
+		}
+		file.write_all(line.as_bytes())
+			.and_then(|_| file.sync_data())
+			.map_err(LgError::io("Journal could not be written."))?;
+		self.records.push(record);
+		Ok(&self.records[self.records.len() - 1])
+	}
+
+	pub fn get(&self, seq: usize) -> Option<&Record> {
+		self.records.iter().find(|r| r.seq == seq)
+	}
+
+	pub fn stacks(&self, profile: &str) -> (Vec<usize>, Vec<usize>) {
+		// Replay the journal into the profile's undo and redo stacks of
+		// `Do` sequence numbers. A fresh change clears the redo stack.
+		let mut undo = Vec::new();
+		let mut redo = Vec::new();
+		for record in self.records.iter().filter(|r| r.profile == profile) {
+			match record.action {
+				Action::Do => {
+					undo.push(record.seq);
+					redo.clear();
+				},
+				Action::Undo(seq) => {
+					undo.retain(|&s| s != seq);
+					redo.push(seq);
+				},
+				Action::Redo(seq) => {
+					redo.retain(|&s| s != seq);
+					undo.push(seq);
+				},
+			}
+		}
+		(undo, redo)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn scratch(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("lg-test-{}-{}", name, std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let log_path = dir.join("log.yml");
+		let _ = std::fs::remove_file(Journal::path(&log_path));
+		log_path
+	}
+
+	#[test]
+	fn appends_past_a_torn_line() {
+		let log_path = scratch("torn");
+		let mut journal = Journal::load(&log_path).unwrap();
+		journal.append("default", vec!["A".to_string()], Action::Do, Vec::new(), Vec::new()).unwrap();
+		// A crash partway through the next append.
+		let mut file = std::fs::OpenOptions::new().append(true).open(Journal::path(&log_path)).unwrap();
+		file.write_all(b"{\"seq\":2,\"ti").unwrap();
+
+		let mut journal = Journal::load(&log_path).unwrap();
+		assert_eq!(journal.records.len(), 1);
+		assert_eq!(journal.skipped, vec![2]);
+		journal.append("default", vec!["B".to_string()], Action::Do, Vec::new(), Vec::new()).unwrap();
+
+		let journal = Journal::load(&log_path).unwrap();
+		let argv = journal.records.iter().map(|r| r.argv.join(" ")).collect::<Vec<_>>();
+		assert_eq!(argv, vec!["A", "B"]);
+		assert_eq!(journal.skipped, vec![2]);
+		assert_eq!(journal.stacks("default").0, vec![1, 2]);
+		std::fs::remove_dir_all(log_path.parent().unwrap()).unwrap();
+	}
+}
@@ -0,0 +1,129 @@
+// //// Kinds //// //
+use std::collections::BTreeMap;
+use super::error::{LgError, Result};
+use super::log::Log;
+
+// How `lg walk` knows a walk is `:Exercise`. Exact aliases are tried
+// first, then the patterns in order, then the kinds the same task was
+// logged under before, then the fallback kind.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct KindRules {
+	pub aliases: BTreeMap<String, String>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	pub patterns: Vec<Pattern>,
+	pub history: History,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub fallback: Option<String>,
+}
+
+// Which past kind of a task to reuse: its most used or its latest.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum History {
+	Frequent,
+	Recent,
+	Off,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Pattern {
+	Glob { glob: String, kind: String },
+	Regex { regex: String, kind: String },
+}
+
+impl Pattern {
+	pub fn kind(&self) -> &str {
+		match self {
+			Pattern::Glob { kind, .. } | Pattern::Regex { kind, .. } => kind,
+		}
+	}
+
+	pub fn source(&self) -> &str {
+		match self {
+			Pattern::Glob { glob, .. } => glob,
+			Pattern::Regex { regex, .. } => regex,
+		}
+	}
+
+	pub fn matches(&self, data: &str) -> Result<bool> {
+		let invalid = |err: String| LgError::Invalid(format!("Kind pattern \"{}\" is invalid.", self.source()), err);
+		match self {
+			Pattern::Glob { glob, .. } => glob::Pattern::new(glob)
+				.map(|glob| glob.matches(data))
+				.map_err(|err| invalid(err.to_string())),
+			Pattern::Regex { regex, .. } => regex::Regex::new(regex)
+				.map(|regex| regex.is_match(data))
+				.map_err(|err| invalid(err.to_string())),
+		}
+	}
+}
+
+impl Default for KindRules {
+	fn default() -> Self {
+		let aliases = [
+			("chatting", "Leisure"),
+			("sleep", "Sleep"),
+			("slp", "Sleep"),
+			("break", "Rest"),
+			("walk", "Exercise"),
+			("trot", "Exercise"),
+			("breakfast", "Meal"),
+			("lunch", "Meal"),
+			("dinner", "Meal"),
+			("game", "Rest"),
+			("distracted", "Distraction"),
+		];
+		Self {
+			aliases: aliases.iter()
+				.map(|(data, kind)| (data.to_string(), kind.to_string()))
+				.collect(),
+			patterns: Vec::new(),
+			history: History::Frequent,
+			fallback: None,
+		}
+	}
+}
+
+impl KindRules {
+	pub fn infer(&self, data: &str, log: &Log) -> Result<Option<String>> {
+		// An empty task is the nil entry, whatever the rules say.
+		if data.is_empty() {
+			return Ok(Some("∅".to_string()));
+		}
+		if let Some(kind) = self.aliases.get(data) {
+			return Ok(Some(kind.to_string()));
+		}
+		for pattern in &self.patterns {
+			if pattern.matches(data)? {
+				return Ok(Some(pattern.kind().to_string()));
+			}
+		}
+		if let Some(kind) = log.kind_history(data, self.history) {
+			return Ok(Some(kind));
+		}
+		Ok(self.fallback.clone())
+	}
+
+	pub fn known(&self) -> Vec<String> {
+		// Every kind the rules can produce, sorted and deduplicated.
+		let mut kinds = self.aliases.values()
+			.map(String::as_str)
+			.chain(self.patterns.iter().map(Pattern::kind))
+			.chain(self.fallback.as_deref())
+			.map(str::to_string)
+			.collect::<Vec<_>>();
+		kinds.sort();
+		kinds.dedup();
+		kinds
+	}
+
+	pub fn remove(&mut self, source: &str) -> bool {
+		// Drop the alias or pattern written as `source`.
+		let before = self.aliases.len() + self.patterns.len();
+		self.aliases.remove(source);
+		self.patterns.retain(|pattern| pattern.source() != source);
+		before != self.aliases.len() + self.patterns.len()
+	}
+}
@@ -1,21 +1,40 @@
 
-use time::*;
+use error::{LgError, Result};
+use time::{format_time, format_datetime};
 //use unicode_segmentation::UnicodeSegmentation;
 extern crate unicode_segmentation;
 extern crate chrono;
 extern crate serde_yaml;
 extern crate serde;
 
-fn unit<T>(_: T) -> () {}
+fn unit<T>(_: T) {}
+
+pub mod color;
+pub mod completions;
+pub mod config;
+pub mod error;
+pub mod format;
+pub mod journal;
+pub mod kinds;
+pub mod list;
+pub mod report;
+pub mod store;
+pub mod views;
 
 pub mod prelude {
 	pub use super::{process_command, execute_command, lg_types};
 	pub use super::log::*;
+	pub use super::config::Config;
+	pub use super::kinds::KindRules;
+	pub use super::time::{Clock, SystemClock, FixedClock};
+	pub use super::store::LogStore;
+	pub use super::error::{LgError, Result};
 }
 
 // //// Log //// //
 pub mod log {
 	use super::lg_types::*;
+	use super::error::{LgError, Result};
 	use unicode_segmentation::UnicodeSegmentation;
 	use chrono::prelude::*;
 
@@ -61,14 +80,21 @@ pub mod log {
 			self.kind == "∅"
 		}
 		pub fn is_empty(&self) -> bool {
-			(self.kind == "") & (self.data == "") & (self.note == "")
+			self.kind.is_empty() & self.data.is_empty() & self.note.is_empty()
+		}
+		pub fn is_identical(&self, other: &LogEntry) -> bool {
+			// `==` compares timestamps alone; this compares content too.
+			(self.time == other.time)
+			&& (self.kind == other.kind)
+			&& (self.data == other.data)
+			&& (self.note == other.note)
 		}
 	}
 
 	impl Default for LogEntry {
 		fn default() -> Self {
 			Self {
-				time: DateTime::parse_from_str("2000 1 0:00 -0700", "%Y %j %H:%M %z").expect("T473"),
+				time: DateTime::parse_from_str("2000 1 0:00 +0000", "%Y %j %H:%M %z").expect("T473"),
 				data: "".to_string(),
 				kind: "".to_string(),
 				note: "".to_string(),
@@ -83,7 +109,7 @@ pub mod log {
 	}
 	impl PartialOrd for LogEntry {
 		fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-			self.time.partial_cmp(&other.time)
+			Some(self.cmp(other))
 		}
 	}
 	//impl Eq for LogEntry {}
@@ -106,60 +132,32 @@ pub mod log {
 		// Running iter() should guarantee datetime-sorted results.
 		pub fn new() -> Log {
 			Log {
-				first: LogEntry::new(DateTime::parse_from_rfc3339("1900-01-01T00:00:00-07:00").unwrap(), "", "", ""),
+				first: LogEntry::new(DateTime::parse_from_rfc3339("1900-01-01T00:00:00+00:00").unwrap(), "", "", ""),
 				vec: Vec::<LogEntry>::new(),
 			}
 		}
 
-		pub fn iter(&self) -> LogIter {
+		pub fn iter(&self) -> LogIter<'_> {
 			self.into_iter()
 		}
 
-		pub fn iter_range(&self, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> LogRangeIter {
+		pub fn iter_range(&self, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> LogRangeIter<'_> {
+			// The task running at `start`, if any, then every one
+			// starting after it through `end`.
 			let mut range: Vec<&LogEntry> = Vec::new();
-			let mut iter = self.iter();
-
-			// Initialize the loop so we can track
-			// every element and its predecessor.
-			let mut prev: &LogEntry = match iter.next() {
-				Some(e) => e,
-				None => return LogRangeIter {
-					index: 0,
-					range: Vec::<&LogEntry>::new(),
-				},
-			};
-
-			// Seek the start time.
-			loop {
-				let task = match iter.next() {
-					Some(e) => e,
-					None => break,
-				};
-
-				// Add the task and its predecessor if it
-				// proved to fall within range after all.
-				if task.time == start {
-					range.push(task);
-					break;
-				} else if task.time > start {
-					range.push(prev);
-					range.push(task);
-					break;
-				}
-				prev = task;
-			}
-
-			// Then iterate normally till the end.
-			for task in iter {
+			for task in self.iter() {
 				if task.time > end {
 					break;
 				}
+				if task.time <= start {
+					range.clear();
+				}
 				range.push(task);
 			}
 
 			LogRangeIter {
 				index: 0,
-				range: range,
+				range,
 			}
 		}
 
@@ -169,16 +167,19 @@ pub mod log {
 			});
 		}
 
-		pub fn update(&mut self, entry: LogEntry) {
+		pub fn update(&mut self, entry: LogEntry) -> Result<()> {
 			// If the timestamp already existed, then edit its content.
 			// If kind, data, and note are _blank_ then remove the entry.
 			// Otherwise, add as a new entry.
 			let (task, index) = self.task_index_at(entry.time);
-			match (entry.is_empty(), entry.time == task.time) {
-				(true, _) => super::unit(self.vec.remove(index)),
+			let exists = (entry.time == task.time) & !self.vec.is_empty();
+			match (entry.is_empty(), exists) {
+				(true, true) => super::unit(self.vec.remove(index)),
+				(true, false) => return Err(LgError::NoEntry(entry.time)),
 				(false, true) => self.vec[index].update(entry.time, &entry.kind, &entry.data, &entry.note),
 				(false, false) => self.push(entry),
 			}
+			Ok(())
 		}
 
 		pub fn push(&mut self, entry: LogEntry) {
@@ -204,13 +205,81 @@ pub mod log {
 			self.vec.sort();
 		}
 
-		pub fn remove(&mut self, time: DateTime<FixedOffset>) -> LogEntry {
+		pub fn remove(&mut self, time: DateTime<FixedOffset>) -> Result<LogEntry> {
 			for (i, entry) in self.vec.iter().enumerate() {
 				if entry.time >= time {
-					return self.vec.remove(i)
+					return Ok(self.vec.remove(i))
 				}
 			}
-			self.vec.remove(self.vec.len())
+			Err(LgError::NoEntry(time))
+		}
+
+		pub fn diff(&self, before: &[LogEntry]) -> (Vec<LogEntry>, Vec<LogEntry>) {
+			// Entries dropped from and added to `before` to arrive at this log,
+			// in one pass over the two, both being sorted by time.
+			let (mut removed, mut added) = (Vec::new(), Vec::new());
+			let mut old = before.iter().peekable();
+			let mut new = self.vec.iter().peekable();
+			loop {
+				match (old.peek(), new.peek()) {
+					(Some(o), Some(n)) if o.is_identical(n) => {
+						old.next();
+						new.next();
+					},
+					(Some(o), Some(n)) if o.time > n.time => added.extend(new.next().cloned()),
+					(Some(o), Some(n)) if o.time < n.time => removed.extend(old.next().cloned()),
+					(Some(_), Some(_)) => {
+						removed.extend(old.next().cloned());
+						added.extend(new.next().cloned());
+					},
+					(Some(_), None) => removed.extend(old.next().cloned()),
+					(None, Some(_)) => added.extend(new.next().cloned()),
+					(None, None) => return (removed, added),
+				}
+			}
+		}
+
+		pub fn apply_diff(&mut self, removed: &[LogEntry], added: &[LogEntry]) -> Result<()> {
+			// Reverse of `diff`: take out `removed` and put in `added`, verbatim.
+			// Refuses, leaving the log untouched, if `removed` isn't all present.
+			if let Some(old) = removed.iter().find(|old| !self.vec.iter().any(|e| e.is_identical(old))) {
+				return Err(LgError::Conflict(format!(
+					"The log has changed; the entry at {} isn't as expected.", old.time.format("%Y-%m-%d %H:%M"))));
+			}
+			self.vec.retain(|e| !removed.iter().any(|old| old.is_identical(e)));
+			self.vec.extend(added.iter().cloned());
+			self.vec.sort();
+			Ok(())
+		}
+
+		pub fn kind_history(&self, data: &str, history: super::kinds::History) -> Option<String> {
+			// The kind `data` was most often, or most lately, logged under.
+			// Ties in frequency go to the latest.
+			use super::kinds::History;
+			let kinds = self.iter()
+				.filter(|e| (e.data == data) & !e.kind.is_empty() & !e.is_nil())
+				.map(|e| e.kind.as_str());
+			match history {
+				History::Off => None,
+				History::Recent => kinds.last().map(str::to_string),
+				History::Frequent => {
+					let mut counts = Vec::<(&str, usize)>::new();
+					for kind in kinds {
+						match counts.iter().position(|(k, _)| *k == kind) {
+							Some(i) => {
+								let (k, n) = counts.remove(i);
+								counts.push((k, n + 1));
+							},
+							None => counts.push((kind, 1)),
+						}
+					}
+					// `max_by_key` keeps the last of equals, and the
+					// most recently used kinds sit at the end.
+					counts.into_iter()
+						.max_by_key(|(_, n)| *n)
+						.map(|(k, _)| k.to_string())
+				},
+			}
 		}
 
 		pub fn first(&self) -> &LogEntry {
@@ -224,7 +293,7 @@ pub mod log {
 			let mut iter = self.iter();
 			let mut pred = iter.next();
 			for elem in iter {
-				if &elem.time == &entry.time {
+				if elem.time == entry.time {
 					return pred;
 				}
 				pred = Some(elem);
@@ -233,10 +302,11 @@ pub mod log {
 		}
 
 		pub fn successor(&self, entry: &LogEntry) -> Option<&LogEntry> {
+			// Entries are told apart by their start, as in `predecessor`.
 			let mut iter = self.iter();
 			loop {
 				match iter.next() {
-					Some(elem) if std::ptr::eq(&elem, &entry) =>
+					Some(elem) if elem.time == entry.time =>
 						return iter.next(),
 					Some(_) => (),
 					None => return None,
@@ -274,11 +344,11 @@ pub mod log {
 			pred
 		}
 
-		pub fn slice(&self, start:DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> LogSlice {
-			let mut iter = self.iter_range(start, end);
+		pub fn slice(&self, start:DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> LogSlice<'_> {
+			let mut iter = self.iter_range(start, end).peekable();
 			// Extract the first entry and set its
 			// starting time to that of the slice.
-			let first = match iter.next() {
+			let first = match iter.next_if(|first| first.time <= start) {
 				Some(first) => LogEntry {
 					time: start,
 					..(*first).clone()
@@ -293,14 +363,29 @@ pub mod log {
 			LogSlice {
 				start_bound: start,
 				end_bound: end,
-				first: first,
+				first,
 				slice: iter
 					.collect::<Box<[&LogEntry]>>(),
 			}
 		}
-		pub fn draw_day(&self, time: DateTime<FixedOffset>, width: usize) -> String {
-			let time = time.with_minute(0).unwrap() + chrono::Duration::hours(1);
-			self.slice(time - chrono::Duration::hours(28), time).draw(width)
+		pub fn day_range(&self, time: DateTime<FixedOffset>, period: chrono::Duration) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+			// The 28 rows through the one holding `time`, which for
+			// hours is the past day and some.
+			let midnight = super::time::local_datetime(time.date_naive().and_time(NaiveTime::MIN));
+			let rows = time.signed_duration_since(midnight).num_minutes() / period.num_minutes().max(1);
+			let end = midnight + period * (rows as i32 + 1);
+			(end - period * 28, end)
+		}
+
+		pub fn draw_day(&self, time: DateTime<FixedOffset>, width: usize, period: chrono::Duration) -> String {
+			let (start, end) = self.day_range(time, period);
+			self.slice(start, end).draw(width, period)
+		}
+	}
+
+	impl Default for Log {
+		fn default() -> Self {
+			Self::new()
 		}
 	}
 
@@ -353,6 +438,75 @@ pub mod log {
 		}
 	}
 
+	fn whole_days(period: chrono::Duration) -> bool {
+		period.num_seconds() % (24 * 60 * 60) == 0
+	}
+
+	fn row_starts(start: DateTime<FixedOffset>, end: DateTime<FixedOffset>, period: chrono::Duration) -> Vec<DateTime<FixedOffset>> {
+		// Where each row from `start` to `end` begins, in local time. Rows
+		// of days or weeks step by wall clock, from midnight to midnight
+		// however long daylight saving makes the day; shorter ones step
+		// by the clock's hands, an hour that repeats being two rows.
+		let period = period.max(chrono::Duration::minutes(1));
+		let start = super::time::to_local(start);
+		match whole_days(period) {
+			true => {
+				let end = super::time::to_local(end).naive_local();
+				(0..)
+					.map(|row| start.naive_local() + period * row)
+					.take_while(|row| *row < end)
+					.map(super::time::local_datetime)
+					.collect()
+			},
+			false => (0..)
+				.map(|row| start + period * row)
+				.take_while(|row| *row < end)
+				.map(super::time::to_local)
+				.collect(),
+		}
+	}
+
+	fn row_label(row: DateTime<FixedOffset>, period: chrono::Duration) -> String {
+		// A row's local time, as precise as its period needs.
+		match period.num_minutes() {
+			m if m < 60 => super::time::format_time(row),
+			m if m < 24 * 60 => super::time::format_hour(row),
+			m if m < 7 * 24 * 60 => row.format("%a %m-%d").to_string(),
+			_ => row.format("%Y-%m-%d").to_string(),
+		}
+	}
+
+	fn ruler(period: chrono::Duration, columns: usize) -> String {
+		// Headings as far apart as fit: minutes into the row, hours and
+		// minutes into it, hours of the day, or weekdays from Monday.
+		let minutes = period.num_minutes().max(1);
+		let scale = columns as f32 / minutes as f32;
+		let monday = DateTime::parse_from_rfc3339("2000-01-03T00:00:00+00:00").unwrap();
+		let label = |offset: i64| match minutes {
+			m if m >= 7 * 24 * 60 => (monday + chrono::Duration::minutes(offset)).format("%a").to_string(),
+			m if m >= 24 * 60 => super::time::format_hour(monday + chrono::Duration::minutes(offset)).trim().to_string(),
+			m if m > 60 => format!("{}:{:0>2}", offset / 60, offset % 60),
+			_ => format!(":{:0>2}", offset),
+		};
+		// Roomily if possible, else just apart.
+		let width = super::display_width(&label(0));
+		let steps = [1, 2, 5, 10, 15, 30, 60, 120, 180, 360, 720, 24 * 60];
+		let fits = |gap: usize| steps.iter()
+			.copied()
+			.find(|step| (minutes % step == 0) & (*step as f32 * scale >= gap as f32));
+		let step = fits(width + 5).or_else(|| fits(width + 1)).unwrap_or(minutes);
+		let mut ruler = String::new();
+		for offset in (0..minutes).step_by(step as usize) {
+			let column = (offset as f32 * scale).round() as usize;
+			ruler.push_str(&" ".repeat(column.saturating_sub(super::display_width(&ruler))));
+			ruler.push_str(&label(offset));
+		}
+		ruler.chars()
+			.chain(std::iter::repeat(' '))
+			.take(columns)
+			.collect()
+	}
+
 	pub struct LogSliceIter<'a> {
 		first: &'a LogEntry,
 		slice: Box<[&'a LogEntry]>,
@@ -379,22 +533,26 @@ pub mod log {
 		slice: Box<[&'a LogEntry]>,
 	}
 
+	// A timeline's rows, each with the time it starts at.
+	pub type Rows = Vec<(DateTime<FixedOffset>, String)>;
+	// The column each entry's run starts at, with its kind.
+	pub type KindRuns<'a> = Vec<(usize, &'a str)>;
+
 	impl<'a> LogSlice<'a> {
 		fn start(&self) -> DateTime<FixedOffset> {
-			self.slice[0].time
-			//self.start_bound
+			self.start_bound
 		}
 		fn end(&self) -> DateTime<FixedOffset> {
 			self.end_bound
 		}
 		fn first(&self) -> &LogEntry {
-			self.slice[0]
+			&self.first
 		}
 		//fn last(&self) -> &LogEntry {
 		//	self.slice[self.slice.len()]
 		//}
 
-		pub fn iter(&self) -> LogSliceIter {
+		pub fn iter(&self) -> LogSliceIter<'_> {
 			LogSliceIter {
 				first: &self.first,
 				slice: self.slice.clone(),
@@ -418,228 +576,502 @@ pub mod log {
 			Some(task)
 		}
 
-		pub fn as_string(&self, period: chrono::Duration, scale: f32, delimit: String) -> (Vec<(DateTime<FixedOffset>, String)>, Vec<&str>) {
-			fn trim_pad(data: &str, len: usize) -> String {
-				// Trim or pad a string with ' ' to make it fit the desired len.
-				match data.chars().count() {
-					// Pad data if short.
-					x if x <= len => [
-							//delimit,
-							data,
-							" ".repeat(len - x).as_str()
-						].concat(),
-					// Trim data if long.
-					_ => UnicodeSegmentation::graphemes(data, true)
-						 .collect::<Vec<&str>>()[..len]
-						 .to_vec()
-						 .join(""),
-				}
+		pub fn as_string(&self, period: chrono::Duration, scale: f32, delimit: String) -> (Rows, Vec<&str>, KindRuns<'_>) {
+			// Rows of `period` each, the kinds drawn in them, and the
+			// column each entry starts at, counted on across rows.
+			// Each entry starts on the column its own time maps to, so
+			// rounding never adds up along a row. Its label runs on into
+			// the next row if need be, but never over the next entry.
+			// Within a row of days, the column is the wall-clock time of
+			// day, as the ruler reads.
+			let columns = ((period.num_minutes() as f32 * scale).round() as usize).max(1);
+			let mut starts = row_starts(self.start_bound, self.end_bound, period);
+			if starts.is_empty() {
+				starts.push(super::time::to_local(self.start_bound));
 			}
-			fn grapheme_slice(start: usize, end: usize, string: &str) -> String {
-				let repr = string.graphemes(true)
-					.collect::<Vec<&str>>()
-					.to_vec();
-				match end {
-					0 => repr[start..].join(""),
-					n if n > repr.len() => repr[start..].join(""),
-					_ => repr[start..end].join(""),
+			let position = |time: DateTime<FixedOffset>| {
+				let row = starts.partition_point(|start| *start <= time).saturating_sub(1);
+				let offset = match whole_days(period) {
+					true => super::time::to_local(time).naive_local() - starts[row].naive_local(),
+					false => time.signed_duration_since(starts[row]),
+				}.num_seconds().max(0);
+				let column = (offset as f32 / 60.0 * scale).round() as usize;
+				row * columns + column.min(columns)
+			};
+
+			let mut cells = vec![" "; starts.len() * columns];
+			let mut key_set = Vec::<&str>::new();
+			let mut runs = Vec::<(usize, &str)>::new();
+			let entries = self.iter().collect::<Vec<_>>();
+			for (i, entry) in entries.iter().enumerate() {
+				let start = position(entry.time).min(cells.len());
+				let end = match entries.get(i + 1) {
+					Some(next) => position(next.time),
+					// The last runs to the end of its row, or of the slice.
+					None => ((start / columns + 1) * columns).min(position(self.end_bound)),
+				}.clamp(start, cells.len());
+				if !key_set.contains(&entry.kind.as_str()) {
+					key_set.push(&entry.kind);
+				}
+				runs.push((start, &entry.kind));
+				// A wide glyph takes two cells, the second left empty. One
+				// that would be cut by the next entry is a space instead,
+				// and one that would be split by a row's end moves past it.
+				let mut cell = start;
+				for glyph in delimit.graphemes(true).chain(entry.data.graphemes(true)) {
+					let width = super::glyph_width(glyph);
+					if cell >= end {
+						break;
+					}
+					if width == 0 {
+						continue;
+					}
+					if (width == 2) & ((cell + 1) % columns == 0) & (cell + 1 < end) {
+						cell += 1;
+					}
+					if cell + width > end {
+						break;
+					}
+					cells[cell] = glyph;
+					if width == 2 {
+						cells[cell + 1] = "";
+					}
+					cell += width;
 				}
-			}
-			fn str_width(string: &str) -> usize {
-				string.graphemes(true)
-					.collect::<Vec<_>>()
-					.len()
 			}
 
-			// Should refactor to guarante timestamp alignment with the horizontal axis when scaling.
-			 /*
+			let rows = starts.iter()
+				.zip(cells.chunks(columns))
+				.map(|(start, row)| (*start, row.concat()))
+				.collect();
+			(rows, key_set, runs)
+		}
 
-			struct EntryDuration {
-				span: chrono::Duration,
-				kind: String,
-				data: String,
-				note: String,
+		pub fn draw(&self, width: usize, period: chrono::Duration) -> String {
+			// Rows of `period` fit to `width` columns, margin and all, between
+			// rulers, then the kinds' legend if in color.
+			let labels = row_starts(self.start_bound, self.end_bound, period).into_iter()
+				.map(|row| row_label(row, period))
+				.collect::<Vec<_>>();
+			let margin = labels.iter().map(|label| super::display_width(label)).max().unwrap_or_default();
+			let columns = width.saturating_sub(margin + 2).max(10);
+			let scale = columns as f32 / period.num_minutes().max(1) as f32;
+			let (rows, key_set, runs) = self.as_string(period, scale, "▌".to_string());
+			let text = rows.into_iter()
+				.map(|(_, line)| line)
+				.collect::<Vec<_>>();
+			let ruler = ruler(period, columns);
+			let mut lines = vec![format!("{}▏{}┃", "▁".repeat(margin), ruler)];
+			for (label, line) in labels.iter().zip(super::color::paint_rows(&text, columns, &runs)) {
+				lines.push(format!("{}{}▏{}┃", " ".repeat(margin - super::display_width(label)), label, line));
+			}
+			lines.push(format!("{}▏{}┃", "▔".repeat(margin), ruler));
+			if super::color::enabled() {
+				lines.push(super::color::legend(&key_set));
 			}
-			struct Period { vec: EntryDuration }
+			lines.join("\n")
+		}
+	}
+}
 
-			let period_len = ;
-			let key_set = Vec::<&str>::new();
-			let periods = Vec::new();
-			// duration, value, kind, note
-			periods.push(Period { vec: Vec::new() });
+// //// Time //// //
+pub mod time {
+	use std::sync::RwLock;
+	use std::sync::atomic::{AtomicBool, Ordering};
+	use chrono::prelude::*;
+	use chrono::{Duration, LocalResult};
+	use chrono_tz::Tz;
+	use super::error::{LgError, Result};
+
+	// The zone wall-clock times are read and written in. Set once per run
+	// by `init_zone`; entries already logged keep their own offsets.
+	static ZONE: RwLock<Option<Tz>> = RwLock::new(None);
+
+	pub fn init_zone(configured: Option<&str>) -> Result<Tz> {
+		// `TZ` if it names a zone, else the configured one, else the
+		// system's, else UTC.
+		let env = std::env::var("TZ").ok()
+			.and_then(|name| name.trim_start_matches(':').parse::<Tz>().ok());
+		let zone = match (env, configured) {
+			(Some(zone), _) => zone,
+			(None, Some(name)) => name.parse::<Tz>()
+				.map_err(|err| LgError::Invalid(format!("Time zone \"{}\" is unknown.", name), err.to_string()))?,
+			(None, None) => iana_time_zone::get_timezone().ok()
+				.and_then(|name| name.parse::<Tz>().ok())
+				.unwrap_or(Tz::UTC),
+		};
+		set_zone(zone);
+		Ok(zone)
+	}
 
-			let mut iter = self.iter();
-			let mut prev = ;
-			for entry in iter {}
-			loop {
-				if event_end_time > next_period {
+	pub fn set_zone(zone: Tz) {
+		*ZONE.write().unwrap() = Some(zone);
+	}
 
-				}
-			}
-			// */
+	pub fn zone() -> Tz {
+		let zone = *ZONE.read().unwrap();
+		match zone {
+			Some(zone) => zone,
+			None => init_zone(None).unwrap_or(Tz::UTC),
+		}
+	}
 
-			let string_cap = (period.num_minutes() as f32 * scale) as usize;
-			let mut key_set = Vec::<&str>::new();
-			let mut rows = Vec::<_>::new();
-			rows.push((self.start_bound, String::new()));
-			let mut last_row = &mut rows[0].1;
+	// How times are printed: `14:05` or `2:05pm`.
+	#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+	pub enum HourFormat {
+		#[serde(rename = "24h")]
+		H24,
+		#[serde(rename = "12h")]
+		H12,
+	}
 
-			let mut iter = self.iter();
-			let mut prev = match iter.next() {
-				Some(e) => {
-					key_set.push(&e.kind);
-					e
-				},
-				None => return (vec![(self.start_bound, "".to_string())], key_set),
-			};
+	static TWELVE_HOUR: AtomicBool = AtomicBool::new(false);
 
-			for entry in iter {
-				// Compute available width.
-				let len = entry.time
-					.signed_duration_since(prev.time)
-					.num_minutes() as usize;
-				let len = (scale * len as f32).round() as usize;
+	pub fn set_hour_format(format: HourFormat) {
+		TWELVE_HOUR.store(format == HourFormat::H12, Ordering::Relaxed);
+	}
 
-				let mut value: String = match len >= str_width(&delimit) {
-					true => [delimit.as_str(), trim_pad(&prev.data, len - str_width(&delimit)).as_str()].concat(),
-					false => grapheme_slice(0, len, &delimit),
-				};
+	pub fn format_time(time: DateTime<FixedOffset>) -> String {
+		match TWELVE_HOUR.load(Ordering::Relaxed) {
+			true => time.format("%-I:%M%P").to_string(),
+			false => time.format("%H:%M").to_string(),
+		}
+	}
 
-				// If longer than the remaining duration/width, split on that boundary and append a new string.
-				let row_remainder: usize = string_cap - str_width(&last_row);
-				match str_width(&value) >= row_remainder {
-					true => {
-						// 
-						last_row.push_str(&grapheme_slice(0, row_remainder, &value));
-						value = grapheme_slice(row_remainder, 0, &value).to_string();
-						// For long values, iteratively split it into new rows.
-						while str_width(&value) >= string_cap {
-							rows.push( (rows[rows.len()-1].0+period, grapheme_slice(0, string_cap, &value)) );
-							value = grapheme_slice(string_cap, 0, &value);
-						}
-						// 
-						if str_width(&rows[rows.len()-1].1) >= string_cap {
-							rows.push( (rows[rows.len()-1].0+period, value.to_string()) );
-						}
-						let index = rows.len()-1;
-						last_row = &mut rows[index].1;
-					},
-					false => last_row.push_str(&value),
-				}
-				prev = entry;
-			}
-
-			// Append the final entry, covering the remaining width.
-			// Computing the remaining space as before.
-			let mut value = [delimit.as_str(), &prev.data].concat();
-			let row_remainder: usize = string_cap - str_width(&last_row);
-			// Filling in that space and/or iterating many lines, as before.
-			match str_width(&value) < row_remainder {
-				true => {
-					value = trim_pad(value.as_str(), row_remainder);
-					last_row.push_str(&value);
-				},
-				false => {
-					last_row.push_str(&grapheme_slice(0, row_remainder, &value));
-					value = grapheme_slice(row_remainder, 0, &value).to_string();
-					// For long values, iteratively split it into new rows.
-					while str_width(&value) >= string_cap {
-						rows.push( (rows[rows.len()-1].0+period, grapheme_slice(0, string_cap, &value)) );
-						value = grapheme_slice(string_cap, 0, &value);
-					}
-					value = trim_pad(value.as_str(), string_cap);
-					rows.push( (rows[rows.len()-1].0+period, value.to_string()) );
-				},
-			}
-			// Fill in blank lines till we pass the slice's end_time.
-			while rows[rows.len()-1].0 + period < self.end_bound {
-				rows.push( (rows[rows.len()-1].0 + period, " ".repeat(string_cap)) );
-			}
-			(rows, key_set)
+	pub fn format_datetime(time: DateTime<FixedOffset>) -> String {
+		format!("{} {}", time.format("%Y-%m-%d"), format_time(time))
+	}
+
+	pub fn format_hour(time: DateTime<FixedOffset>) -> String {
+		// Three columns wide, for the timeline's margin: `14:` or ` 2p`.
+		match TWELVE_HOUR.load(Ordering::Relaxed) {
+			true => format!("{:>2}{}", time.hour12().1, if time.hour12().0 { 'p' } else { 'a' }),
+			false => format!("{:0>2}:", time.hour()),
 		}
+	}
 
-		pub fn draw(&self, width: usize) -> String {
-			let day_log = self
-				.as_string(chrono::Duration::minutes(60), width as f32/60.0, "▌".to_string())
-				.0;
-			day_log.iter()
-					.map(|row| format!("{:0>2}:▏{}┃", row.0.hour(), row.1))
-					.collect::<Vec<_>>()
-					.join("\n")
+	// Where "now" comes from, so commands can be run as of another moment.
+	pub trait Clock {
+		fn now(&self) -> DateTime<FixedOffset>;
+	}
+
+	pub struct SystemClock;
+
+	impl Clock for SystemClock {
+		fn now(&self) -> DateTime<FixedOffset> {
+			now()
 		}
 	}
-}
 
-// //// Time //// //
-mod time {
-	use chrono::prelude::*;
-	use chrono::Duration;
+	pub struct FixedClock(pub DateTime<FixedOffset>);
+
+	impl Clock for FixedClock {
+		fn now(&self) -> DateTime<FixedOffset> {
+			self.0
+		}
+	}
 
 	pub fn now() -> DateTime<FixedOffset> {
-		//DateTime::<FixedOffset>::from_utc(Utc::now().naive_utc(), FixedOffset)
-		Utc::now().with_timezone(&FixedOffset::west(7 * 3600))
+		Utc::now().with_timezone(&zone())
+						.fixed_offset()
 						.with_second(0).unwrap()
 						.with_nanosecond(0).unwrap()
-		//Local::now().with_timezone(&Utc)
 	}
 
-	fn parse_time_str(time_str: &str) -> Vec<i64> {
+	fn parse_time_str(time_str: &str) -> Option<Vec<i64>> {
 		// Convert user input into numerals.
 		// 0:30 => 0, 30
 		// 1: => 1, 0
 		let mut time_units = Vec::<i64>::new();
-		for unit in time_str.split(":") {
+		for unit in time_str.split(':') {
 			// Parse the substrings into numbers.
 			let unit = match unit {
 				"" => Ok(0),
 				_ => unit.parse::<i64>(),
 			};
-
-			let unit = match unit {
-				Ok(unit) => unit,
-				Err(err) => panic!("Invalid timestamp--must be of `1:`, `:30`, or `30` format. {}", err),
-			};
-			time_units.push(unit);
+			time_units.push(unit.ok()?);
 		}
-		time_units
+		Some(time_units)
 	}
 
-	pub fn parse_time(time_str: &str) -> NaiveTime {
+	pub fn parse_time(time_str: &str) -> Result<NaiveTime> {
 		//DateTime::parse_from_str("2019 14 1:46 +0000", "%Y %j %H:%M %z").expect("T475")
-		let time_units: Vec<u32> = parse_time_str(time_str)
-			.into_iter()
-			.map(|n| n as u32)
-			.collect();
+		let invalid = || LgError::InvalidTime(time_str.to_string());
+		let lower = time_str.to_lowercase();
+		// `3pm` and `11:30am` name hours 1 through 12.
+		let (clock_str, pm) = match lower.as_str() {
+			"noon" => ("12:", Some(true)),
+			"midnight" => ("12:", Some(false)),
+			_ => match (lower.strip_suffix("am"), lower.strip_suffix("pm")) {
+				(Some(clock_str), _) if !clock_str.is_empty() => (clock_str, Some(false)),
+				(_, Some(clock_str)) if !clock_str.is_empty() => (clock_str, Some(true)),
+				_ => (lower.as_str(), None),
+			},
+		};
+		let mut time_units = parse_time_str(clock_str).ok_or_else(invalid)?;
+		if let Some(pm) = pm {
+			match time_units.as_mut_slice() {
+				[hour @ 1..=12] | [hour @ 1..=12, _] => *hour = *hour % 12 + if pm { 12 } else { 0 },
+				_ => return Err(invalid()),
+			}
+			// A lone `3pm` is an hour, not minutes.
+			if time_units.len() == 1 {
+				time_units.push(0);
+			}
+		}
 		// Convert into minute totals.
-		let minutes = match time_units.len() {
-			2 => NaiveTime::from_num_seconds_from_midnight_opt( time_units[0]*3600 + time_units[1]*60, 0),
-			1 => NaiveTime::from_num_seconds_from_midnight_opt( time_units[0]*60, 0),
-			_ => panic!("Invalid timestamp--must be of `0:30` or `30` format.")
+		let seconds = match time_units.len() {
+			2 => time_units[0].checked_mul(3600).zip(time_units[1].checked_mul(60)).and_then(|(h, m)| h.checked_add(m)),
+			1 => time_units[0].checked_mul(60),
+			_ => None,
 		};
-		match minutes {
-			Some(m) => m,
-			None => panic!("Not a valid timestamp! Must be of range 0:00-23:59. {}", time_str)
+		match seconds {
+			Some(s) if (0..86400).contains(&s) => NaiveTime::from_num_seconds_from_midnight_opt(s as u32, 0).ok_or_else(invalid),
+			_ => Err(invalid()),
 		}
 	}
 
-	pub fn parse_duration(time_str: &str) -> Duration {
-		let time_units = parse_time_str(time_str);
-		// Convert into minute totals.
-		let minutes = match time_units.len() {
-			2 => time_units[0]*60 + time_units[1],
-			1 => time_units[0],
-			_ => panic!("Invalid timestamp--must be of `0:30` or `30` format.")
+	fn parse_units(time_str: &str) -> Option<f64> {
+		// Sum `1h30m`, `1.5h` or `2d` into seconds.
+		let mut seconds = 0.0;
+		let mut number = String::new();
+		for c in time_str.chars() {
+			let unit = match c {
+				'0'..='9' | '.' => {
+					number.push(c);
+					continue;
+				},
+				'd' => 86400.0,
+				'h' => 3600.0,
+				'm' => 60.0,
+				's' => 1.0,
+				_ => return None,
+			};
+			seconds += number.parse::<f64>().ok()? * unit;
+			number.clear();
+		}
+		match number.is_empty() & !time_str.is_empty() {
+			true => Some(seconds),
+			false => None,
+		}
+	}
+
+	pub fn parse_duration(time_str: &str) -> Result<Duration> {
+		let invalid = || LgError::InvalidDuration(time_str.to_string());
+		// Convert into second totals.
+		let seconds = match time_str.chars().all(|c| c.is_ascii_digit() | (c == ':')) {
+			// `1:30` or bare minutes.
+			true => {
+				let time_units = parse_time_str(time_str).ok_or_else(invalid)?;
+				match time_units.len() {
+					2 => time_units[0].checked_mul(60).and_then(|h| h.checked_add(time_units[1])),
+					1 => Some(time_units[0]),
+					_ => None,
+				}.and_then(|m| m.checked_mul(60))
+			},
+			false => parse_units(time_str).map(|s| s.round() as i64),
+		};
+		match seconds {
+			Some(s) if (0..=i64::from(i32::MAX) * 60).contains(&s) => Ok(Duration::seconds(s)),
+			_ => Err(invalid()),
+		}
+	}
+
+	pub fn parse_date(date_str: &str, today: NaiveDate) -> Option<NaiveDate> {
+		// `today`, `yesterday`, a weekday (the latest on or before today),
+		// `-2d` days ago (before a time), `2019-08-10`, `08-10` or `Aug10`
+		// (the latest such day), or `19Aug10`.
+		match date_str.to_lowercase().as_str() {
+			"today" => return Some(today),
+			"yesterday" => return today.pred_opt(),
+			"tomorrow" => return today.succ_opt(),
+			_ => (),
+		}
+		if let Ok(weekday) = date_str.parse::<Weekday>() {
+			let back = (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+			return today.checked_sub_signed(Duration::days(i64::from(back)));
+		}
+		if let Some(days) = date_str.strip_prefix('-').and_then(|d| d.strip_suffix('d')) {
+			return today.checked_sub_signed(Duration::try_days(days.parse::<i64>().ok()?)?);
+		}
+		if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
+			return Some(date);
+		}
+		let latest = |month, day| match NaiveDate::from_ymd_opt(today.year(), month, day) {
+			Some(date) if date <= today => Some(date),
+			_ => NaiveDate::from_ymd_opt(today.year() - 1, month, day),
 		};
-		Duration::minutes(minutes)
+		if let Some(start) = date_str.find(|c: char| c.is_ascii_alphabetic()) {
+			let end = date_str.rfind(|c: char| c.is_ascii_alphabetic())? + 1;
+			let month = NaiveDate::parse_from_str(&format!("2000{}1", &date_str[start..end]), "%Y%b%d").ok()?.month();
+			let day = date_str[end..].parse::<u32>().ok()?;
+			return match &date_str[..start] {
+				"" => latest(month, day),
+				year if year.len() == 2 => NaiveDate::from_ymd_opt(2000 + year.parse::<i32>().ok()?, month, day),
+				year => NaiveDate::from_ymd_opt(year.parse::<i32>().ok()?, month, day),
+			};
+		}
+		let (month, day) = date_str.split_once('-')?;
+		latest(month.parse::<u32>().ok()?, day.parse::<u32>().ok()?)
+	}
+
+	pub fn is_date(date_str: &str, today: NaiveDate) -> bool {
+		parse_date(date_str, today).is_some()
+	}
+
+	pub fn parse_timestamp(time_str: &str, today: NaiveDate) -> Result<(Option<NaiveDate>, NaiveTime)> {
+		// Split `yesterday 14:00` or `2019-08-10T17:15` into its date and
+		// time. A lone date means its midnight; a lone time has no date.
+		let invalid = || LgError::InvalidTime(time_str.to_string());
+		let (date_str, time_str) = match time_str.split_once(char::is_whitespace) {
+			Some((date, time)) => (Some(date), time.trim()),
+			None => match time_str.split_once('T') {
+				Some((date, time)) if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() => (Some(date), time),
+				_ if time_str.contains(':') => (None, time_str),
+				_ => match parse_date(time_str, today) {
+					Some(date) => return Ok((Some(date), NaiveTime::MIN)),
+					None => (None, time_str),
+				},
+			},
+		};
+		let date = match date_str {
+			Some(date_str) => Some(parse_date(date_str, today).ok_or_else(invalid)?),
+			None => None,
+		};
+		Ok((date, parse_time(time_str).map_err(|_| invalid())?))
+	}
+
+	fn parse_moment(time_str: &str, now: DateTime<FixedOffset>, bare: impl FnOnce(NaiveTime) -> DateTime<FixedOffset>) -> Result<DateTime<FixedOffset>> {
+		// A dated timestamp is taken as is; `-15m` is that long ago; a
+		// bare time is left to `bare`.
+		if let Ok(time) = DateTime::parse_from_rfc3339(time_str) {
+			return Ok(time);
+		}
+		if let Some(Ok(span)) = time_str.strip_prefix('-').map(parse_duration) {
+			return Ok(now - span);
+		}
+		if let Some(Ok(span)) = time_str.strip_prefix('+').map(parse_duration) {
+			return Ok(now + span);
+		}
+		Ok(match parse_timestamp(time_str, now.date_naive())? {
+			(Some(date), time) => local_datetime(date.and_time(time)),
+			(None, time) => bare(time),
+		})
+	}
+
+	pub fn parse_datetime(time_str: &str, frame: DateTime<FixedOffset>, now: DateTime<FixedOffset>) -> Result<DateTime<FixedOffset>> {
+		// A bare time is its first occurrence after `frame`.
+		parse_moment(time_str, now, |time| map_time_after_datetime(time, frame))
+	}
+
+	pub fn parse_start(time_str: &str, now: DateTime<FixedOffset>, window: &Window) -> Result<DateTime<FixedOffset>> {
+		// `>9:00` is the next 9:00 and `<9:00` the last; a bare `9:00` is
+		// whichever `window` picks.
+		if let Some(time_str) = time_str.strip_prefix('>') {
+			return Ok(map_time_after_datetime(parse_time(time_str)?, now));
+		}
+		if let Some(time_str) = time_str.strip_prefix('<') {
+			return Ok(map_time_after_datetime(parse_time(time_str)?, now - Duration::days(1)));
+		}
+		let (back, ahead) = window.spans()?;
+		parse_moment(time_str, now, |time| resolve_in_window(time, now, back, ahead, window.prefer))
+	}
+
+	// Which day a bare `@9:00` falls on: the occurrences from `back`
+	// before now through `ahead` after it, and which of them is preferred
+	// should there be several, or nearest should there be none.
+	#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+	#[serde(default)]
+	pub struct Window {
+		pub back: String,
+		pub ahead: String,
+		pub prefer: Bias,
+	}
+
+	#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+	#[serde(rename_all = "lowercase")]
+	pub enum Bias {
+		Past,
+		Future,
+		Nearest,
+	}
+
+	impl Default for Window {
+		fn default() -> Self {
+			Self {
+				back: "12h".to_string(),
+				ahead: "12h".to_string(),
+				prefer: Bias::Past,
+			}
+		}
+	}
+
+	impl Window {
+		pub fn spans(&self) -> Result<(Duration, Duration)> {
+			// How far back and ahead it reaches.
+			let invalid = |err: LgError| LgError::Invalid("Config window is invalid.".to_string(), err.to_string());
+			Ok((parse_duration(&self.back).map_err(invalid)?, parse_duration(&self.ahead).map_err(invalid)?))
+		}
+	}
+
+	fn resolve_in_window(time: NaiveTime, now: DateTime<FixedOffset>, back: Duration, ahead: Duration, prefer: Bias) -> DateTime<FixedOffset> {
+		let mut candidates = Vec::new();
+		let mut candidate = map_time_after_datetime(time, now - back);
+		while candidate <= now + ahead {
+			candidates.push(candidate);
+			candidate = map_time_after_datetime(time, candidate);
+		}
+		// Failing any in the window, the nearest on either side.
+		if candidates.is_empty() {
+			candidates.push(map_time_after_datetime(time, now - Duration::days(1)));
+			candidates.push(map_time_after_datetime(time, now));
+		}
+		let before = candidates.iter().rev().find(|c| **c <= now);
+		let after = candidates.iter().find(|c| **c > now);
+		let nearest = candidates.iter().min_by_key(|c| (**c - now).num_seconds().abs());
+		*match prefer {
+			Bias::Past => before.or(after),
+			Bias::Future => after.or(before),
+			Bias::Nearest => nearest,
+		}.expect("No candidate time!")
+	}
+
+	pub fn to_local(time: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+		// The same instant, read in the local zone.
+		time.with_timezone(&zone()).fixed_offset()
+	}
+
+	pub fn local_datetime(time: NaiveDateTime) -> DateTime<FixedOffset> {
+		// A wall time in the local zone, the earlier reading if it repeats.
+		resolve_local(zone(), time)[0].fixed_offset()
+	}
+
+	fn resolve_local(zone: Tz, time: NaiveDateTime) -> Vec<DateTime<Tz>> {
+		// Every instant the wall time names in the zone: one usually, two
+		// when clocks fall back. One skipped when clocks spring forward is
+		// read with the offset from before the jump, landing just after it.
+		match zone.from_local_datetime(&time) {
+			LocalResult::Single(t) => vec![t],
+			LocalResult::Ambiguous(early, late) => vec![early, late],
+			LocalResult::None => {
+				let before = zone.offset_from_utc_datetime(&(time - Duration::days(1)));
+				vec![zone.from_utc_datetime(&(time - before.fix()))]
+			},
+		}
 	}
 
 	pub fn map_time_after_datetime(time: NaiveTime, date_frame: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
 		// Input time and date and output the earliest datetime matching that time.
-		// I.e. `3:00AM` and `Wed 5:00PM` output `Tue, 3:00AM`.
-		let time = date_frame.date().and_time(time).expect("Invalid date + time!");
-		match date_frame.time() < time.time() {
-			true => time,
-			false => time + Duration::days(1),
+		// I.e. `3:00AM` and `Wed 5:00PM` output `Thu, 3:00AM`.
+		let zone = zone();
+		let mut date = date_frame.with_timezone(&zone).date_naive();
+		loop {
+			let found = resolve_local(zone, date.and_time(time)).into_iter()
+				.find(|t| *t > date_frame);
+			if let Some(t) = found {
+				return t.fixed_offset();
+			}
+			date = date.succ_opt().expect("Date out of range!");
 		}
 	}
 }
@@ -650,11 +1082,13 @@ pub mod lg_types {
 	use chrono::Duration;
 	use super::time::*;
 	use super::log::*;
+	use super::error::{LgError, Result};
 
 	#[derive(Debug, PartialEq, Clone)]
 	pub enum RawInit {
 		Now,
 		Retcon,
+		RetconAt(String),
 		Time(String),
 	}
 
@@ -701,10 +1135,11 @@ pub mod lg_types {
 	}
 
 	impl RawStatement {
-		pub fn compile(&self, log: &mut Log) -> Option<ValidStatement> {
+		pub fn compile(&self, log: &mut Log, clock: &dyn Clock, window: &Window) -> Result<ValidStatement> {
+			let now = clock.now();
 			let time = match self.init.clone() {
-				RawInit::Now => now(),
-				RawInit::Retcon => log.task_at(now()).time,
+				RawInit::Now => now,
+				RawInit::Retcon => log.task_at(now).time,
 				/*
 				RawInit::Retcon => match log.task_at(now()).kind.as_str() {
 					"∅" => {
@@ -715,27 +1150,28 @@ pub mod lg_types {
 					},
 					_ => now(),
 				},*/
-				RawInit::Time(t) => map_time_after_datetime(parse_time(&t), now() - Duration::hours(12)),
+				RawInit::Time(t) => parse_start(&t, now, window)?,
+				// Retcon whichever task was running then, if any was.
+				RawInit::RetconAt(t) => match log.task_at(parse_start(&t, now, window)?) {
+					task if task.is_empty() => return Err(LgError::NothingToRetcon),
+					task => task.time,
+				},
 			};
 			let init = match self.init {
 				RawInit::Now => ValidInit::Now(time),
-				RawInit::Retcon => ValidInit::Retcon(time),
+				RawInit::Retcon | RawInit::RetconAt(_) => ValidInit::Retcon(time),
 				RawInit::Time(_) => ValidInit::Time(time),
 			};
 			//let init = ValidInit::Time(time);
 			let till = match self.till.clone() {
 				RawTill::Nil => ValidTill::Nil,
-				RawTill::For(t) => ValidTill::For(parse_duration(&t)),
-				RawTill::Till(t) => ValidTill::Till(
-					map_time_after_datetime(parse_time(&t), time - Duration::hours(12))),
-			};
-			let data = match &self.data {
-				Some(d) => Some(d.clone()),
-				None => None,
+				RawTill::For(t) => ValidTill::For(parse_duration(&t)?),
+				RawTill::Till(t) => ValidTill::Till(parse_datetime(&t, time, now)?),
 			};
+			let data = self.data.clone();
 			//map_time_after_datetime(parse_time(&t), now() - Duration::hours(12))
 			//map_time_after_datetime(parse_time(&t), start_time - Duration::hours(12))
-			Some(ValidStatement { init, data, till })
+			Ok(ValidStatement { init, data, till })
 		}
 	}
 
@@ -792,7 +1228,18 @@ fn join_logs(logs: Vec<(String, usize)>) -> String {
 }
 */
 
-pub fn execute_command(cmd: lg_types::ValidStatement, log: &mut log::Log) -> Vec<log::LogEntry> {
+pub fn glyph_width(glyph: &str) -> usize {
+	// Terminal cells a grapheme takes: 2 for CJK and emoji, 0 for a
+	// lone combining mark. A cluster never takes more than a wide glyph.
+	unicode_width::UnicodeWidthStr::width(glyph).min(2)
+}
+
+pub fn display_width(text: &str) -> usize {
+	// Terminal cells a string takes, as `glyph_width` counts them.
+	unicode_segmentation::UnicodeSegmentation::graphemes(text, true).map(glyph_width).sum()
+}
+
+pub fn execute_command(cmd: lg_types::ValidStatement, log: &mut log::Log) -> Result<Vec<log::LogEntry>> {
 	use lg_types::ValidInit::{Retcon, Now, Time};
 	use lg_types::ValidTill::{Nil, For, Till};
 	// Given a retcon signal, should we rewrite the _current_
@@ -802,6 +1249,7 @@ pub fn execute_command(cmd: lg_types::ValidStatement, log: &mut log::Log) -> Vec
 		// Minimize cost if the user makes a mistake. (Rewrite only if nil.)
 	// Former is easier to code. Do that.
 
+	let retcon = matches!(cmd.init, Retcon(_));
 	let time = match cmd.init {
 		Retcon(t) => t,
 		Now(t) => t,
@@ -810,66 +1258,87 @@ pub fn execute_command(cmd: lg_types::ValidStatement, log: &mut log::Log) -> Vec
 	let end = match cmd.till {
 		Nil => None,
 		For(duration) => Some(time + duration),
-		Till(t) => Some(map_time_after_datetime(t.time(), time)),
+		Till(t) if t <= time => return Err(LgError::Refused(format!(
+			"Entry would end at {} before it starts.", format_datetime(t)))),
+		Till(t) => Some(t),
 	};
 
-	// Record the task.
-	let mut vec = Vec::new();
-	let data = cmd.data.unwrap();
-	vec.push( log::LogEntry::new(time, &data.kind, &data.data, &data.note) );
+	let mut data = cmd.data.ok_or(LgError::MissingData)?;
+	if retcon & data.data.is_empty() & !data.kind.is_empty() {
+		// `lg _ :Kind` keeps the current task and changes only its kind.
+		let task = log.task_at(time);
+		// Nil, or the log's empty start before any entry.
+		if task.is_nil() | task.is_empty() {
+			return Err(LgError::NothingToRetcon);
+		}
+		data.data = task.data.clone();
+		if data.note.is_empty() {
+			data.note = task.note.clone();
+		}
+	}
 
-	// Record the task's endpoint, if specified, deleting the intervening events.
+	// Delete the events the task's span covers, if specified, before
+	// recording it: pushing the task merges it into a following entry of
+	// the same content, which would leave that deletion nothing to delete.
+	let mut vec = Vec::new();
+	let mut end_task = None;
 	if let Some(t) = end {
 		let task = log.task_at(t);
 		if (task.time >= time) & (task.time < t) {
 			// Rebuild the last overlapped task
 			// at the end and delete the rest.
-			let end_task = log::LogEntry::new(t, &task.kind, &task.data, &task.note);
-			let slice = log.slice(time, t);
-			for log::LogEntry { time, .. } in slice.iter() {
-				vec.push(log::LogEntry::empty(*time));
+			end_task = Some(log::LogEntry::new(t, &task.kind, &task.data, &task.note));
+			// An entry starting right at `time` is replaced by the new task
+			// itself, so only those strictly inside the span are deleted.
+			for log::LogEntry { time: inner, .. } in log.iter().filter(|e| (e.time > time) & (e.time < t)) {
+				vec.push(log::LogEntry::empty(*inner));
 			}
-			vec.push(end_task);
 		} else {
 			// Insert nil at the end.
-			vec.push( log::LogEntry::nil(t) );
+			end_task = Some(log::LogEntry::nil(t));
 		}
 	}
-	vec
+
+	// Record the task, then its endpoint.
+	vec.push( log::LogEntry::new(time, &data.kind, &data.data, &data.note) );
+	vec.extend(end_task);
+	Ok(vec)
 }
 
-use chrono::Timelike;
-pub fn process_command(cmd: lg_types::RawStatement, log: &mut log::Log) -> Vec<log::LogEntry> {
+pub fn process_command(cmd: lg_types::RawStatement, log: &mut log::Log, clock: &dyn time::Clock, window: &time::Window, format: format::Format) -> Result<Vec<log::LogEntry>> {
 	use lg_types::ValidStatement;
 	use lg_types::ValidInit::{Retcon, Now, Time};
 	use lg_types::ValidTill::{Nil, For, Till};
-	let cmd = match cmd.compile(log) {
-		Some(cmd) => cmd,
-		None => panic!("Command didn't compile!"),
-	};
-	match &cmd {
+	let cmd = cmd.compile(log, clock, window)?;
+	Ok(match &cmd {
 		// lg [ _ | @ | • ] [ - | + | • ] [ task | '' | • ]
 		ValidStatement { init: _, till: _, data: Some(_) } =>
 			// lg * * task|''
-			execute_command(cmd, log),
+			execute_command(cmd, log)?,
 		ValidStatement { init: Retcon(time), till: Nil, data: None } => {
 			// lg _ • •
 			// Print the active and preceding entry.
 			let task = log.task_at(*time);
+			if format != format::Format::Text {
+				let items = log.predecessor(task).into_iter()
+					.map(|pred| list::Item::new(pred, Some(task), clock.now()))
+					.chain(Some(list::Item::new(task, log.successor(task), clock.now())))
+					.collect::<Vec<_>>();
+				format::print(format, &items);
+				return Ok(Vec::new());
+			}
 			if let Some(pred) = log.predecessor(task) {
-				println!("{:0>2}:{:0>2}-{:0>2}:{:0>2} \t{}: {} - {}",
-					pred.time.hour(), pred.time.minute(),
-					task.time.hour(), task.time.minute(),
+				println!("{}-{} \t{}: {} - {}",
+					format_time(pred.time), format_time(task.time),
 					pred.kind, pred.data, pred.note
 				);
 			}
 			match log.successor(task) {
-				Some(succ) => println!("{:0>2}:{:0>2}-{:0>2}:{:0>2} \t{}: {} - {}",
-					task.time.hour(), task.time.minute(),
-					succ.time.hour(), succ.time.minute(),
+				Some(succ) => println!("{}-{} \t{}: {} - {}",
+					format_time(task.time), format_time(succ.time),
 					task.kind, task.data, task.note),
-				None => println!("{:0>2}:{:0>2}  ...  \t{}: {} - {}",
-					task.time.hour(), task.time.minute(),
+				None => println!("{}  ...  \t{}: {} - {}",
+					format_time(task.time),
 					task.kind, task.data, task.note),
 			}
 			Vec::<log::LogEntry>::new()
@@ -877,9 +1346,12 @@ pub fn process_command(cmd: lg_types::RawStatement, log: &mut log::Log) -> Vec<l
 		ValidStatement { init: Now(time), till: Nil, data: None } => {
 			// lg • • •
 			// Retrieve and print the day.
-			println!("▁▁▁▏:00       :10       :20       :30       :40       :50       ┃");
-			println!("{}", log.draw_day(time.clone(), 60));
-			println!("▔▔▔▏:00       :10       :20       :30       :40       :50       ┃");
+			if format == format::Format::Text {
+				println!("{}", log.draw_day(*time, views::width(), views::period()));
+			} else {
+				let (start, end) = log.day_range(*time, views::period());
+				format::print(format, &list::Listing::new(log, start, end, *time).items);
+			}
 			Vec::<log::LogEntry>::new()
 		},
 		ValidStatement { init: Time(time), till: Nil, data: None }
@@ -898,7 +1370,7 @@ pub fn process_command(cmd: lg_types::RawStatement, log: &mut log::Log) -> Vec<l
 			while task.is_nil() {
 				task = match log.predecessor(task) {
 					Some(t) => t,
-					None => panic!("No available (not nil) task to retcon!"),
+					None => return Err(LgError::NothingToRetcon),
 				};
 			}
 			vec![ log::LogEntry::nil(task.time + *duration) ]
@@ -913,6 +1385,260 @@ pub fn process_command(cmd: lg_types::RawStatement, log: &mut log::Log) -> Vec<l
 			println!("Please specify a task name to log.");
 			Vec::<log::LogEntry>::new()
 		},
-	}
+	})
 }
 
+#[cfg(test)]
+mod tests {
+	use chrono::prelude::*;
+	use chrono::Duration;
+	use super::prelude::*;
+	use super::format::Format;
+	use super::lg_types::{RawInit, RawStatement, RawTill, ValidData, ValidInit};
+	use super::time::{self, Bias, Window};
+
+	pub fn at(time: &str) -> DateTime<FixedOffset> {
+		// Every test reads wall times in the same zone.
+		time::set_zone(chrono_tz::America::Los_Angeles);
+		DateTime::parse_from_rfc3339(time).unwrap()
+	}
+
+	fn log(entries: &[(&str, &str)]) -> Log {
+		let mut log = Log::new();
+		for (time, data) in entries {
+			log.add(at(time), data.to_string(), "Work".to_string(), "".to_string());
+		}
+		log
+	}
+
+	fn compile_start(init: RawInit, clock: &dyn Clock) -> DateTime<FixedOffset> {
+		let statement = RawStatement { init, till: RawTill::Nil, data: None };
+		match statement.compile(&mut Log::new(), clock, &Window::default()).unwrap().init {
+			ValidInit::Now(time) | ValidInit::Retcon(time) | ValidInit::Time(time) => time,
+		}
+	}
+
+	#[test]
+	fn parse_start_reads_times_from_the_clock() {
+		let window = Window::default();
+		let clock = FixedClock(at("2026-10-18T17:00:00-07:00"));
+		assert_eq!(time::parse_start("7:00", clock.now(), &window).unwrap(), at("2026-10-18T07:00:00-07:00"));
+		assert_eq!(time::parse_start("-15m", clock.now(), &window).unwrap(), at("2026-10-18T16:45:00-07:00"));
+		assert_eq!(time::parse_start("yesterday 14:00", clock.now(), &window).unwrap(), at("2026-10-17T14:00:00-07:00"));
+		// Late in the evening, a morning is tomorrow's.
+		let clock = FixedClock(at("2026-10-18T22:00:00-07:00"));
+		assert_eq!(time::parse_start("7:00", clock.now(), &window).unwrap(), at("2026-10-19T07:00:00-07:00"));
+	}
+
+	#[test]
+	fn parse_start_resolves_in_the_given_window() {
+		let now = at("2026-10-18T20:00:00-07:00");
+		assert_eq!(time::parse_start("9:00", now, &Window::default()).unwrap(), at("2026-10-18T09:00:00-07:00"));
+		let ahead = Window { back: "2h".to_string(), ahead: "14h".to_string(), prefer: Bias::Past };
+		assert_eq!(time::parse_start("9:00", now, &ahead).unwrap(), at("2026-10-19T09:00:00-07:00"));
+	}
+
+	#[test]
+	fn compile_reads_next_and_last_from_the_clock() {
+		let clock = FixedClock(at("2026-10-18T09:30:00-07:00"));
+		let start = |time: &str| compile_start(RawInit::Time(time.to_string()), &clock);
+		assert_eq!(start(">9:00"), at("2026-10-19T09:00:00-07:00"));
+		assert_eq!(start("<9:00"), at("2026-10-18T09:00:00-07:00"));
+		assert_eq!(start(">10:00"), at("2026-10-18T10:00:00-07:00"));
+		assert_eq!(start("<10:00"), at("2026-10-17T10:00:00-07:00"));
+		assert_eq!(compile_start(RawInit::Now, &clock), clock.now());
+	}
+
+	#[test]
+	fn iter_range_starts_with_the_running_entry() {
+		let log = log(&[("2026-10-18T09:00:00-07:00", "A"), ("2026-10-18T10:00:00-07:00", "B"),
+			("2026-10-18T11:00:00-07:00", "C"), ("2026-10-18T12:00:00-07:00", "D")]);
+		let range = |start, end| log.iter_range(at(start), at(end)).map(|e| e.data.as_str()).collect::<Vec<_>>();
+		assert_eq!(range("2026-10-18T09:30:00-07:00", "2026-10-18T11:00:00-07:00"), vec!["A", "B", "C"]);
+		assert_eq!(range("2026-10-18T10:00:00-07:00", "2026-10-18T10:30:00-07:00"), vec!["B"]);
+		assert_eq!(range("2026-10-18T08:00:00-07:00", "2026-10-18T09:30:00-07:00"), vec!["A"]);
+		assert_eq!(range("2026-10-18T12:30:00-07:00", "2026-10-18T13:00:00-07:00"), vec!["D"]);
+		assert!(range("2026-10-18T07:00:00-07:00", "2026-10-18T08:00:00-07:00").is_empty());
+	}
+
+	#[test]
+	fn slice_clips_its_first_entry_to_the_start() {
+		let log = log(&[("2026-10-18T09:00:00-07:00", "A"), ("2026-10-18T10:00:00-07:00", "B")]);
+		let slice = log.slice(at("2026-10-18T09:30:00-07:00"), at("2026-10-18T11:00:00-07:00"));
+		let entries = slice.iter().map(|e| (e.time, e.data.as_str())).collect::<Vec<_>>();
+		assert_eq!(entries, vec![(at("2026-10-18T09:30:00-07:00"), "A"), (at("2026-10-18T10:00:00-07:00"), "B")]);
+		// Before the first entry, the slice opens on nil.
+		let slice = log.slice(at("2026-10-18T08:00:00-07:00"), at("2026-10-18T09:30:00-07:00"));
+		let entries = slice.iter().map(|e| (e.time, e.is_nil())).collect::<Vec<_>>();
+		assert_eq!(entries, vec![(at("2026-10-18T08:00:00-07:00"), true), (at("2026-10-18T09:00:00-07:00"), false)]);
+		// Inside a single entry, it is that entry alone.
+		let slice = log.slice(at("2026-10-18T10:15:00-07:00"), at("2026-10-18T10:45:00-07:00"));
+		assert_eq!(slice.iter().map(|e| e.data.as_str()).collect::<Vec<_>>(), vec!["B"]);
+		assert_eq!(slice.task_at(at("2026-10-18T10:20:00-07:00")).map(|e| e.data.as_str()), Some("B"));
+		assert_eq!(slice.end_bound - slice.start_bound, Duration::minutes(30));
+	}
+
+	#[test]
+	fn hour_rows_follow_the_clock_across_fall_back() {
+		// 1:00 comes twice on 2026-11-01; 1:30 PST is in the second.
+		let log = log(&[("2026-10-31T22:00:00-07:00", "Sleep"), ("2026-11-01T01:30:00-08:00", "Awake")]);
+		let slice = log.slice(at("2026-11-01T00:00:00-07:00"), at("2026-11-01T05:00:00-08:00"));
+		let (rows, _, _) = slice.as_string(Duration::hours(1), 0.5, "".to_string());
+		let starts = rows.iter().map(|(start, _)| *start).collect::<Vec<_>>();
+		assert_eq!(starts, vec![at("2026-11-01T00:00:00-07:00"), at("2026-11-01T01:00:00-07:00"),
+			at("2026-11-01T01:00:00-08:00"), at("2026-11-01T02:00:00-08:00"),
+			at("2026-11-01T03:00:00-08:00"), at("2026-11-01T04:00:00-08:00")]);
+		assert_eq!(rows[2].1.trim(), "Awake");
+		assert_eq!(rows[2].1.find('A'), Some(15));
+	}
+
+	#[test]
+	fn diff_finds_the_entries_changed() {
+		let before = log(&[("2026-10-18T09:00:00-07:00", "A"), ("2026-10-18T10:00:00-07:00", "B"),
+			("2026-10-18T11:00:00-07:00", "C")]);
+		let after = log(&[("2026-10-18T08:00:00-07:00", "Z"), ("2026-10-18T09:00:00-07:00", "A"),
+			("2026-10-18T10:00:00-07:00", "b"), ("2026-10-18T12:00:00-07:00", "D")]);
+		let data = |entries: Vec<LogEntry>| entries.into_iter().map(|e| e.data).collect::<Vec<_>>();
+		let (removed, added) = after.diff(&before.vec);
+		assert_eq!((data(removed), data(added)), (vec!["B".to_string(), "C".to_string()], vec!["Z".to_string(), "b".to_string(), "D".to_string()]));
+		assert_eq!(before.diff(&before.vec), (Vec::new(), Vec::new()));
+	}
+
+	#[test]
+	fn a_span_may_merge_into_the_entry_it_covers() {
+		// `lg B :Work @9:30 -10:30` moves B up over the 10:00 B it covers.
+		let mut log = log(&[("2026-10-18T09:00:00-07:00", "A"), ("2026-10-18T10:00:00-07:00", "B"),
+			("2026-10-18T11:00:00-07:00", "C")]);
+		let statement = RawStatement {
+			init: RawInit::Time("9:30".to_string()),
+			till: RawTill::Till("10:30".to_string()),
+			data: Some(ValidData { kind: "Work".to_string(), data: "B".to_string(), note: "".to_string() }),
+		};
+		let clock = FixedClock(at("2026-10-18T12:00:00-07:00"));
+		for entry in process_command(statement, &mut log, &clock, &Window::default(), Format::Text).unwrap() {
+			log.update(entry).unwrap();
+		}
+		let entries = log.iter().map(|e| (e.time, e.data.as_str())).collect::<Vec<_>>();
+		assert_eq!(entries, vec![(at("2026-10-18T09:00:00-07:00"), "A"), (at("2026-10-18T09:30:00-07:00"), "B"),
+			(at("2026-10-18T11:00:00-07:00"), "C")]);
+	}
+
+	#[test]
+	fn rekinding_needs_a_task() {
+		let statement = || RawStatement {
+			init: RawInit::Retcon,
+			till: RawTill::Nil,
+			data: Some(ValidData { kind: "Work".to_string(), data: "".to_string(), note: "".to_string() }),
+		};
+		let clock = FixedClock(at("2026-10-18T12:00:00-07:00"));
+		let result = process_command(statement(), &mut Log::new(), &clock, &Window::default(), Format::Text);
+		assert!(matches!(result, Err(LgError::NothingToRetcon)));
+		let mut log = log(&[("2026-10-18T09:00:00-07:00", "A")]);
+		let entries = process_command(statement(), &mut log, &clock, &Window::default(), Format::Text).unwrap();
+		assert_eq!(entries.iter().map(|e| (e.time, e.data.as_str())).collect::<Vec<_>>(), vec![(at("2026-10-18T09:00:00-07:00"), "A")]);
+	}
+
+	#[test]
+	fn retcon_at_needs_a_task_then() {
+		let clock = FixedClock(at("2026-10-18T12:00:00-07:00"));
+		let statement = RawStatement { init: RawInit::RetconAt("-2d 9:00".to_string()), till: RawTill::Nil, data: None };
+		let mut log = log(&[("2026-10-18T09:00:00-07:00", "A")]);
+		assert!(matches!(statement.compile(&mut log, &clock, &Window::default()), Err(LgError::NothingToRetcon)));
+		let statement = RawStatement { init: RawInit::RetconAt("9:30".to_string()), ..statement };
+		assert!(matches!(statement.compile(&mut log, &clock, &Window::default()).unwrap().init,
+			ValidInit::Retcon(time) if time == at("2026-10-18T09:00:00-07:00")));
+	}
+
+	#[test]
+	fn days_ago_out_of_range_are_invalid() {
+		let window = Window::default();
+		let now = at("2026-10-18T12:00:00-07:00");
+		assert_eq!(time::parse_start("-2d 9:00", now, &window).unwrap(), at("2026-10-16T09:00:00-07:00"));
+		assert!(matches!(time::parse_start("-99999999999999d 9:00", now, &window), Err(LgError::InvalidTime(_))));
+		assert_eq!(time::parse_date("-99999999999999d", now.date_naive()), None);
+	}
+
+	#[test]
+	fn durations_sum_their_units() {
+		let duration = |text| time::parse_duration(text).map_err(|err| err.to_string());
+		assert_eq!(duration("1h30m"), Ok(Duration::minutes(90)));
+		assert_eq!(duration("90m"), Ok(Duration::minutes(90)));
+		assert_eq!(duration("1.5h"), Ok(Duration::minutes(90)));
+		assert_eq!(duration("2d"), Ok(Duration::days(2)));
+		assert_eq!(duration("1:30"), Ok(Duration::minutes(90)));
+		assert_eq!(duration(":15"), Ok(Duration::minutes(15)));
+		assert_eq!(duration("45"), Ok(Duration::minutes(45)));
+		for text in ["1.5.5h", "1h30", "h", "1x", "99999999999999999999d", "99999999999999:00"] {
+			assert!(matches!(time::parse_duration(text), Err(LgError::InvalidDuration(_))), "{}", text);
+		}
+	}
+
+	#[test]
+	fn twelve_hour_times_parse() {
+		let time = |text| time::parse_time(text).ok();
+		let hm = |h, m| NaiveTime::from_hms_opt(h, m, 0);
+		assert_eq!(time("3pm"), hm(15, 0));
+		assert_eq!(time("11:30am"), hm(11, 30));
+		assert_eq!(time("12am"), hm(0, 0));
+		assert_eq!(time("12pm"), hm(12, 0));
+		assert_eq!(time("noon"), hm(12, 0));
+		assert_eq!(time("midnight"), hm(0, 0));
+		assert_eq!(time("3PM"), hm(15, 0));
+		for text in ["13pm", "0am", "pm"] {
+			assert!(matches!(time::parse_time(text), Err(LgError::InvalidTime(_))), "{}", text);
+		}
+	}
+
+	#[test]
+	fn twelve_hour_times_print() {
+		// The hour format is process-wide; no other test prints times.
+		let morning = at("2026-10-18T09:05:00-07:00");
+		let evening = at("2026-10-18T21:05:00-07:00");
+		let midnight = at("2026-10-18T00:30:00-07:00");
+		time::set_hour_format(time::HourFormat::H12);
+		let twelve = [morning, evening, midnight].map(|t| (time::format_time(t), time::format_hour(t)));
+		time::set_hour_format(time::HourFormat::H24);
+		let twenty_four = [morning, evening, midnight].map(|t| (time::format_time(t), time::format_hour(t)));
+		let strings = |pairs: [(String, String); 3]| pairs.map(|(time, hour)| format!("{}|{}", time, hour));
+		assert_eq!(strings(twelve), ["9:05am| 9a", "9:05pm| 9p", "12:30am|12a"]);
+		assert_eq!(strings(twenty_four), ["09:05|09:", "21:05|21:", "00:30|00:"]);
+	}
+
+	fn rows(entries: &[(&str, &str)], start: &str, end: &str, period: Duration, scale: f32) -> Vec<String> {
+		let log = log(entries);
+		let slice = log.slice(at(start), at(end));
+		let (rows, _, _) = slice.as_string(period, scale, "".to_string());
+		rows.into_iter().map(|(_, row)| row).collect()
+	}
+
+	#[test]
+	fn wide_glyphs_fit_their_cells() {
+		// Cut by the next entry, a wide glyph leaves a space.
+		let cut = rows(&[("2026-10-18T00:00:00-07:00", "日本語"), ("2026-10-18T00:03:00-07:00", "x")],
+			"2026-10-18T00:00:00-07:00", "2026-10-18T00:10:00-07:00", Duration::minutes(10), 1.0);
+		assert_eq!(cut, vec!["日 x      "]);
+		// One that a row's end would split starts the next row instead.
+		let wrapped = rows(&[("2026-10-18T00:00:00-07:00", ""), ("2026-10-18T00:09:00-07:00", "日本"), ("2026-10-18T00:15:00-07:00", "x")],
+			"2026-10-18T00:00:00-07:00", "2026-10-18T00:20:00-07:00", Duration::minutes(10), 1.0);
+		assert_eq!(wrapped, vec!["          ", "日本 x    "]);
+		// Combining marks ride on their letter; a lone one takes no cell.
+		let marks = rows(&[("2026-10-18T00:00:00-07:00", "Cafe\u{301}"), ("2026-10-18T00:05:00-07:00", "\u{301}ab")],
+			"2026-10-18T00:00:00-07:00", "2026-10-18T00:10:00-07:00", Duration::minutes(10), 1.0);
+		assert_eq!(marks, vec!["Cafe\u{301} ab   "]);
+		assert_eq!(super::display_width(&marks[0]), 10);
+	}
+
+	#[test]
+	fn entries_start_on_their_own_columns_across_rows() {
+		// At 0.7 columns a minute, each start rounds on its own, so none
+		// drifts: 0:40 is column 28, and 1:20 and 1:59 are 14 and 41 of
+		// the second row.
+		let log = log(&[("2026-10-18T00:00:00-07:00", "a"), ("2026-10-18T00:40:00-07:00", "b"),
+			("2026-10-18T01:20:00-07:00", "c"), ("2026-10-18T01:59:00-07:00", "d")]);
+		let slice = log.slice(at("2026-10-18T00:00:00-07:00"), at("2026-10-18T02:00:00-07:00"));
+		let (rows, _, runs) = slice.as_string(Duration::hours(1), 0.7, "".to_string());
+		assert_eq!(runs.iter().map(|(column, _)| *column).collect::<Vec<_>>(), vec![0, 28, 56, 83]);
+		assert_eq!(rows.iter().map(|(_, row)| row.find(|c| c != ' ')).collect::<Vec<_>>(), vec![Some(0), Some(14)]);
+		assert_eq!(rows[1].1.rfind('d'), Some(41));
+	}
+}
@@ -0,0 +1,128 @@
+// //// List //// //
+use std::fmt;
+use chrono::prelude::*;
+use chrono::Duration;
+use super::color;
+use super::display_width;
+use super::format;
+use super::log::{Log, LogEntry};
+use super::time::format_time;
+
+// One line per entry over a range, for pasting into standups and
+// timesheets: start, end, duration, kind, task and note. Untracked
+// time is left out; it shows as the gap between one's end and the
+// next one's start. As records, an item is its entry's fields, then
+// `end`, `duration` and `running`.
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Item<'a> {
+	#[serde(flatten)]
+	pub entry: &'a LogEntry,
+	// When the next entry starts; none for the last.
+	pub end: Option<DateTime<FixedOffset>>,
+	#[serde(serialize_with = "format::seconds")]
+	pub duration: Duration,
+	// Still going now, the duration being the time elapsed so far.
+	pub running: bool,
+}
+
+impl format::Record for Item<'_> {
+	const COLUMNS: &'static [&'static str] = &["time", "data", "kind", "note", "end", "duration", "running"];
+}
+
+#[derive(Debug, Clone)]
+pub struct Listing<'a> {
+	pub items: Vec<Item<'a>>,
+}
+
+impl<'a> Item<'a> {
+	pub fn new(entry: &'a LogEntry, next: Option<&LogEntry>, now: DateTime<FixedOffset>) -> Item<'a> {
+		// `next` is the entry after `entry`, ending it.
+		let end = next.map(|next| next.time);
+		let running = (entry.time <= now) & end.is_none_or(|end| end > now);
+		let duration = match (running, end) {
+			(true, _) => now.signed_duration_since(entry.time),
+			(false, Some(end)) => end.signed_duration_since(entry.time),
+			(false, None) => Duration::zero(),
+		};
+		Item { entry, end, duration, running }
+	}
+}
+
+impl<'a> Listing<'a> {
+	pub fn new(log: &'a Log, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>, now: DateTime<FixedOffset>) -> Listing<'a> {
+		// One pass, each entry paired with the next; the last in range
+		// with the first after it.
+		let range = log.iter_range(start, end).collect::<Vec<_>>();
+		let after = range.last().and_then(|last| log.successor(last));
+		let nexts = range.iter().skip(1).copied().chain(after).map(Some).chain(std::iter::once(None));
+		let items = range.iter()
+			.zip(nexts)
+			.filter(|(entry, _)| (entry.time < end) & !entry.is_nil() & !entry.is_empty())
+			.map(|(entry, next)| Item::new(entry, next, now))
+			.collect();
+		Listing { items }
+	}
+}
+
+fn format_duration(duration: Duration) -> String {
+	format!("{}:{:0>2}", duration.num_hours(), duration.num_minutes() % 60)
+}
+
+impl fmt::Display for Listing<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// A date heading starts each day. The running entry reads `now`
+		// for its end, and is bold in color.
+		let pad = |text: &str, width: usize| format!("{}{}", text, " ".repeat(width - display_width(text)));
+		let kind_width = self.items.iter().map(|item| display_width(&item.entry.kind)).max().unwrap_or_default();
+		let data_width = self.items.iter().map(|item| display_width(&item.entry.data)).max().unwrap_or_default();
+		let mut lines = Vec::new();
+		let mut date = None;
+		for item in &self.items {
+			if date != Some(item.entry.time.date_naive()) {
+				date = Some(item.entry.time.date_naive());
+				lines.push(item.entry.time.format("%a %Y-%m-%d").to_string());
+			}
+			let end = match (item.running, item.end) {
+				(true, _) => "now".to_string(),
+				(false, Some(end)) => format_time(end),
+				(false, None) => "…".to_string(),
+			};
+			let line = format!("{:>7} {:>7} {:>6}  {}  {}  {}",
+				format_time(item.entry.time), end, format_duration(item.duration),
+				color::paint(&pad(&item.entry.kind, kind_width), &item.entry.kind),
+				pad(&item.entry.data, data_width),
+				item.entry.note);
+			lines.push(match item.running {
+				true => color::bold(line.trim_end()),
+				false => line.trim_end().to_string(),
+			});
+		}
+		write!(f, "{}", lines.join("\n"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::at;
+
+	#[test]
+	fn items_end_where_the_next_entry_starts() {
+		let mut log = Log::new();
+		for (time, data) in [("2026-10-18T09:00:00-07:00", "A"), ("2026-10-18T10:00:00-07:00", "B"), ("2026-10-18T12:00:00-07:00", "C")] {
+			log.add(at(time), data.to_string(), "Work".to_string(), "".to_string());
+		}
+		let listing = Listing::new(&log, at("2026-10-18T09:30:00-07:00"), at("2026-10-18T11:00:00-07:00"), at("2026-10-18T12:30:00-07:00"));
+		let items = listing.items.iter()
+			.map(|item| (item.entry.data.as_str(), item.end, item.duration, item.running))
+			.collect::<Vec<_>>();
+		assert_eq!(items, vec![
+			("A", Some(at("2026-10-18T10:00:00-07:00")), Duration::hours(1), false),
+			("B", Some(at("2026-10-18T12:00:00-07:00")), Duration::hours(2), false),
+		]);
+		let listing = Listing::new(&log, at("2026-10-18T11:00:00-07:00"), at("2026-10-18T13:00:00-07:00"), at("2026-10-18T12:30:00-07:00"));
+		let items = listing.items.iter().map(|item| (item.entry.data.as_str(), item.end, item.running)).collect::<Vec<_>>();
+		assert_eq!(items, vec![("B", Some(at("2026-10-18T12:00:00-07:00")), false), ("C", None, true)]);
+	}
+}
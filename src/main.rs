@@ -1,37 +1,43 @@
 use ::lg::prelude::*;
 use lg_types::{ValidData, RawInit, RawTill, RawStatement};
+use lg::journal::{Action, Journal};
+use lg::format::Format;
+use lg::kinds::Pattern;
+use lg::store::{self, backup_path, write_atomic, Backend};
+use lg::list::Listing;
+use lg::report::{By, Report};
+use lg::views::Span;
 use std::env;
 use std::collections::HashMap;
+use std::fs::TryLockError;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use unicode_segmentation::UnicodeSegmentation;
 extern crate unicode_segmentation;
 extern crate chrono;
 extern crate serde_yaml;
 extern crate serde;
 
-fn infer_kind(data: &str) -> Option<String> {
-	match data {
-		"" => Some("∅".to_string()),
-		"chatting" => Some("Leisure".to_string()),
-		"sleep" => Some("Sleep".to_string()),
-		"slp" => Some("Sleep".to_string()),
-		"break" => Some("Rest".to_string()),
-		"walk" => Some("Exercise".to_string()),
-		"trot" => Some("Exercise".to_string()),
-		"breakfast" => Some("Meal".to_string()),
-		"lunch" => Some("Meal".to_string()),
-		"dinner" => Some("Meal".to_string()),
-		"game" => Some("Rest".to_string()),
-		"distracted" => Some("Distraction".to_string()),
-		data => panic!("Entry is of unknown kind! {}", data),
-	}
-}
-
-
-
-
 fn print_help() {
+	let yaml = clap::load_yaml!("cli.yml");
+	let _ = clap::App::from_yaml(yaml).version(clap::crate_version!()).print_help();
 	let msg = r#"
 	lg [ _ | @ | • ] [ - | + | • ] [ task | '' | • ]
+	lg --file PATH --profile NAME --now TIME --color WHEN --width N --period P --format F ...
+	lg profiles [list | create NAME | rename OLD NEW | delete NAME | default NAME]
+	lg restore [N]
+	lg undo | lg redo | lg history [N]
+	lg migrate --to sqlite|yaml
+	lg kinds [list | add TASK KIND | add GLOB KIND | add --regex REGEX KIND | remove TASK | fallback [KIND]]
+	lg add TASK [--kind K] [--at T] [--till T | --for D] [NOTE]
+	lg view [--current]
+	lg hr [N | TIME-TIME] | lg day [N | DATE-DATE] | lg week [N | DATE-DATE]
+	lg report [N | DATE-DATE | TIME-TIME] [--by data|note]
+	lg list [N | DATE-DATE | TIME-TIME]
+	lg edit TIME [--task T] [--kind K] [--note N] [--till T | --for D] [--delete]
+	lg config [show | path | get KEY | set KEY VALUE | unset KEY]
+	lg completions bash|zsh|fish
+	lg help SUBCOMMAND, or lg SUBCOMMAND --help, for each one's options.
 
 	Input a task and category type/kind to log it immediately.
 	lg "Task Name" :MyCategory
@@ -45,12 +51,44 @@ fn print_help() {
 	To review the events of the past 24 hours, pass no arguments.
 	lg
 
+	The chart fits the terminal's width, or `--width N` columns.
+	Its rows are an hour each; `--period 15m` (or 30m, 2h, 1d), or
+	`period:` in the config, changes that.
+		lg --period 15m
+
+	For longer or other spans, draw one row per hour, day or week.
+	Give a count of rows back from now, or a range.
+		lg hr 24		# A day
+		lg day 28		# Four weeks
+		lg week 24		# Six months
+		lg day Feb4-Feb10	# Each from the latest such date
+		lg day mon-today
+		lg hr 14:37-15:00
+		lg hr :37-:00		# This hour's, or the last
+		lg hr 14-16
+	(A task named `day`, `hr` or `week` is logged with `lg add day`.)
+
+	`lg report` totals the hours per kind, today's or over a range
+	as for `lg day` or `lg hr`, with each kind's share of the tracked
+	time and the untracked (∅) time. `--by data` or `--by note`
+	splits each kind's total further.
+		lg report mon-today
+		lg report 7 --by data
+		lg report 9:00-17:30
+
+	`lg list` prints one line per entry instead, over the same
+	ranges: its start, end, duration, kind, task and note. The
+	running entry ends `now`, with the time it has run so far.
+		lg list yesterday
+
 	Use the `@` flag to specify when it starts.
 	Use the `-` or `+` flags to specify when it ends.
 	`@13:00` Starts _at_ 13:00.
 	`-14:45` Lasts _till_ 14:45.
 	`+2:00` Lasts _for_ two hours.
 	`+:15` Lasts _for_ fifteen minutes.
+	`+1h30m`, `+90m`, `+1.5h`, `+2d` Durations in units of d, h, m, s.
+	`@-15m` Started fifteen minutes ago.
 		lg Running :Exercise
 		lg Running :Exercise @14:00 -14:45
 		lg Running :Exercise @14:00 +:45
@@ -64,6 +102,28 @@ fn print_help() {
 		lg _ -15:00
 		lg _ Jogging :Exercise
 
+	`@` and `-` also take a date before the time, for past days:
+	`today`, `yesterday`, a weekday (the latest one), `-2d` for two
+	days ago, `08-10` (the latest August 10th), or a full date.
+	A date alone means its midnight.
+		lg Reading :Leisure @yesterday 14:00 -15:30
+		lg @mon 9:00 :
+		lg Deploy :Work @2019-08-10T17:15 -2019-08-10T18:00
+	Add `_` to retcon whatever was running then.
+		lg _ @-2d 9:00 :Work
+
+	Leave out `:Kind` and it is inferred from the task name: first by
+	exact alias, then by glob or regex pattern, then by the kind the
+	task was most often logged as before, then by a fallback.
+	`lg kinds` lists these rules and `lg kinds add` extends them.
+	Set `kinds: {history: recent}` in the config to prefer the kind
+	it was last logged as, or `off` to ignore the log.
+	An inferred kind is printed; `lg _ :Kind` corrects it.
+		lg kinds add standup Work
+		lg kinds add 'meet*' Work
+		lg kinds add --regex '^(bus|train)' Commute
+		lg kinds fallback Misc
+
 	Retcon with no additional specifiers simply tell you
 	what the current and preceding events _are._
 
@@ -72,14 +132,73 @@ fn print_help() {
 		lg @11:00 :
 		lg _ :
 
-	Do note that `@` and `-` specify time periods from 12
-	hours in the past and 12 hours in the future. So, if
-	it is now midnight, `@7:00` will reference _tomorrow_
-	morning, not _this_ morning.
-	So, if it is now 17:00 in the afternoon, you can only
-	reference between 5:00 this morning to 4:49 tomorrow.
-	Likewise, if it's 22:00, you can reference between
-	10:00 in the morning to 9:49 tomorrow morning.
+	The log is kept in `--file PATH` if given, else in `$LG_FILE`,
+	else in `$XDG_DATA_HOME/lg/log.yml` (`~/.local/share/lg/log.yml`).
+	A missing log file is started afresh.
+
+	`--now TIME` runs a command as though it were issued at TIME,
+	which takes any `@` form, e.g. for backfilling from a script:
+		lg --now 'yesterday 18:00' Cooking :Meal +45m
+
+	One file may hold several people's logs, one per profile.
+	`--profile NAME` picks one; otherwise the `profile` set in
+	`$XDG_CONFIG_HOME/lg/config.yml` (or by `lg profiles default`)
+	is used, or the file's only profile if it has just one. Only
+	`lg profiles create NAME` adds a profile; naming one that isn't
+	there, or choosing none among several, is an error.
+
+	Each write keeps the previous file as `log.yml.1`, shifting
+	older copies up to the configured `backups` count (5).
+	`lg restore` lists them; `lg restore 2` rolls back to one.
+
+	A log path ending in `.sqlite` is kept in an indexed SQLite
+	database rather than YAML; `lg migrate --to sqlite` converts
+	the current log (and `--to yaml` converts back) and makes
+	that the default. SQLite logs rely on transactions, not backups.
+
+	Every change is also journaled beside the log (`log.yml.journal`).
+	`lg undo` reverts the latest change, `lg redo` reapplies it,
+	and `lg history` lists the recent ones.
+
+	Times are read in the zone named by `TZ`, else the config's
+	`timezone` (e.g. `Europe/Berlin`), else the system's. Each entry
+	keeps the UTC offset it was logged with.
+
+	In a terminal, each kind is drawn in its own color, with a
+	legend below. `--color=always` or `--color=never` (or `color:`
+	in the config) overrides that; `NO_COLOR` turns it off too.
+	Pick kinds' colors by name or 256-color number in the config:
+		colors: {Work: blue, Sleep: bright-black, Meal: 208}
+
+	`--format json`, `jsonl` or `csv` prints the bare `lg` chart, the
+	`hr`, `day` and `week` charts, `lg list`, `lg report` and the
+	retcon summary as records for scripts: each entry's time, data,
+	kind and note, then its end, its duration in seconds, and whether
+	it is running. Report rows are each kind's and part's duration and
+	share of the tracked time, then the untracked time.
+		lg --format jsonl list mon-today | jq -r .data
+		lg --format csv report 7 --by data > week.csv
+
+	Times may also be given on a 12-hour clock: `@3pm`, `-11:30am`,
+	`@noon`, `@midnight`. Set `hour_format: 12h` in the config to
+	print them that way too.
+
+	`lg completions SHELL` prints a completion script; e.g. add
+	`source <(lg completions bash)` to ~/.bashrc. It completes
+	subcommands, `:Kind` from the log and the kind rules, recent
+	task names, and `@`/`-` times at the current task's edges.
+
+	Concurrent `lg` runs take turns on the log. One that changes
+	it waits up to `lock_timeout` seconds (10) for the others.
+
+	A bare `@` time means its occurrence from 12 hours ago to 12
+	hours ahead: at 17:00, `@7:00` is this morning; at 22:00, it
+	is tomorrow morning. `@<7:00` forces the last 7:00 and `@>7:00`
+	the next one. A `-` time is the first after the entry starts.
+	The window is set in the config, e.g. for night shifts:
+		window: {back: 18h, ahead: 6h, prefer: past}
+	`prefer` (past, future, or nearest) breaks ties when the window
+	spans over a day, and picks the side when it spans under one.
 
 "#;
 	println!("{}", msg);
@@ -102,16 +221,13 @@ enum CLIArgType<'a> {
 }
 
 
-fn match_arg_type(arg: &str) -> CLIArgType {
+fn match_arg_type(arg: &str) -> CLIArgType<'_> {
 	if (arg == "-h") | (arg == "--help") {
 		return CLIArgType::Flag(CLIFlag::Help);
 	}
 
 	let mut arg_iter = UnicodeSegmentation::graphemes(arg, true);
-	let prefix: &str = match arg_iter.next() {
-		Some(chr) => chr,
-		None => "",
-	};
+	let prefix: &str = arg_iter.next().unwrap_or_default();
 	let term: &str = arg_iter.as_str();
 	match prefix {
 		"_" => CLIArgType::Retcon,
@@ -123,7 +239,7 @@ fn match_arg_type(arg: &str) -> CLIArgType {
 	}
 }
 
-fn parse_commit_args<'a>(args: Vec<&str>) -> Option<RawStatement> {
+fn parse_commit_args(args: Vec<&str>, rules: &KindRules, log: &Log, clock: &dyn Clock) -> Result<Option<RawStatement>> {
 	//use lg_types::{RawStatement, RawInit, RawTill};
 	let mut init = RawInit::Now;
 	let mut till = RawTill::Nil;
@@ -132,77 +248,232 @@ fn parse_commit_args<'a>(args: Vec<&str>) -> Option<RawStatement> {
 	let mut note = Vec::<&str>::new();
 
 	// Parse arguments into their appropriate types.
-	for arg in args {
+	let mut args = args.into_iter().peekable();
+	while let Some(arg) = args.next() {
+		// `@yesterday 14:00` may come as one argument or two.
+		let mut stamp = |t: &str| match args.peek() {
+			Some(next) if lg::time::is_date(t, clock.now().date_naive())
+				& matches!(match_arg_type(next), CLIArgType::Data(_))
+				& (next.contains(':') | lg::time::parse_time(next).is_ok()) =>
+				format!("{} {}", t, args.next().unwrap_or_default()),
+			_ => t.to_string(),
+		};
 		match match_arg_type(arg) {
-			CLIArgType::Flag(CLIFlag::Help) => {print_help(); return None;},
-			CLIArgType::Retcon if init != RawInit::Now => panic!("Retcon \"_\" flag already used!"),
-			CLIArgType::AtTime(t) if init != RawInit::Now => panic!("\"@00:00\" or retcon \"_\" flag already used! @{}", t),
-			CLIArgType::TillTime(t) if till != RawTill::Nil => panic!("\"+/-\" flag already used! -{}", t),
-			CLIArgType::ForTime(t) if till != RawTill::Nil => panic!("\"+/-\" flag already used! +{}", t),
-			CLIArgType::Kind(k) if kind.is_some() => panic!("Entry kind already specified! :{}", k),
+			CLIArgType::Flag(CLIFlag::Help) => {print_help(); return Ok(None);},
+			// `_ @T` retcons the task running at T.
+			CLIArgType::Retcon => init = match init {
+				RawInit::Now => RawInit::Retcon,
+				RawInit::Time(t) => RawInit::RetconAt(t),
+				_ => return Err(LgError::DuplicateFlag(arg.to_string())),
+			},
+			CLIArgType::AtTime(t) => init = match init {
+				RawInit::Now => RawInit::Time(stamp(t)),
+				RawInit::Retcon => RawInit::RetconAt(stamp(t)),
+				_ => return Err(LgError::DuplicateFlag(arg.to_string())),
+			},
+			CLIArgType::TillTime(_) if till != RawTill::Nil => return Err(LgError::DuplicateFlag(arg.to_string())),
+			CLIArgType::ForTime(_) if till != RawTill::Nil => return Err(LgError::DuplicateFlag(arg.to_string())),
+			CLIArgType::Kind(_) if kind.is_some() => return Err(LgError::DuplicateFlag(arg.to_string())),
 			CLIArgType::Data(d) if data.is_some() => note.push(d),
-			CLIArgType::Retcon => init = RawInit::Retcon,//InstrInit::RetNone,
-			CLIArgType::AtTime(t) => init = RawInit::Time(t.to_string()),//InstrInit::Time(parse_time(t)),
-			CLIArgType::TillTime(t) => till = RawTill::Till(t.to_string()),//InstrTill::Halt(parse_time(t)),
+			CLIArgType::TillTime(t) => till = RawTill::Till(stamp(t)),//InstrTill::Halt(parse_time(t)),
 			CLIArgType::ForTime(t) => till = RawTill::For(t.to_string()),//InstrTill::Span(parse_duration(t)),
 			CLIArgType::Kind(k) => kind = Some(k.to_string()),
 			CLIArgType::Data(d) => data = Some(d.to_string()),
 		}
 	}
 
+	let data = make_data(kind, data, note.join(" "), &init, rules, log)?;
+	Ok(Some(RawStatement { init, till, data }))
+}
+
+fn make_data(mut kind: Option<String>, data: Option<String>, note: String, init: &RawInit, rules: &KindRules, log: &Log) -> Result<Option<ValidData>> {
 	// Data-kind-note validity check.
 	// If data is available at all, both kind and data must be present.
 	if let (None, Some(d)) = (&kind, &data) {
-		kind = infer_kind(d);
+		kind = match rules.infer(d, log)? {
+			Some(kind) => Some(kind),
+			None => return Err(LgError::UnknownKind(d.to_string(), rules.known())),
+		};
+		// Say what was guessed, so a wrong guess gets noticed.
+		match kind.as_deref() {
+			Some("∅") | None => (),
+			Some(k) => eprintln!("{} :{} \t(`lg _ :Kind` to correct)", d, k),
+		}
 	}
 
-	let note = note.join(" ");
-
-	let data: Option<ValidData> = match (kind, data, note.len() > 0) {
+	Ok(match (kind, data, !note.is_empty()) {
 		(None, None, false) => None,
 		(Some(kind), Some(data), _) => Some(ValidData { kind, data, note }),
-		(Some(ref kind), None, _) if kind == "" => Some(ValidData { kind: "".to_string(), data: "".to_string(), note }),
-		(Some(_), None, _) => panic!("No data provided."),
-		(_, Some(_), _) => panic!("No kind provided."),
-		(None, None, true) => panic!("Notes provided but no kind nor data."),
-	};
-	Some(RawStatement { init, till, data })
+		(Some(ref kind), None, _) if kind.is_empty() => Some(ValidData { kind: "".to_string(), data: "".to_string(), note }),
+		// `lg _ :Kind` re-kinds the current task, keeping its data.
+		(Some(kind), None, _) if matches!(init, RawInit::Retcon | RawInit::RetconAt(_)) => Some(ValidData { kind, data: "".to_string(), note }),
+		(Some(_), None, _) => return Err(LgError::MissingData),
+		(_, Some(data), _) => return Err(LgError::MissingKind(data)),
+		(None, None, true) => return Err(LgError::NoteWithoutData),
+	})
 }
 
-fn read_log(file_path: &str) -> HashMap<String, Log> {
-	// Serde load; ensure correctly sorted
-	let file_path = std::path::Path::new(file_path);
-	let file = std::fs::File::open(file_path).expect("Log file not found.");
-	let min_log: HashMap<String, Vec<LogEntry>> = serde_yaml::from_reader(file).expect("Log file is invalid.");
-	//let min_log = HashMap::new();//HashMap<String, Vec<LogEntry>>
-	//load minlog
-	let mut full_log = HashMap::new();//HashMap<String, Log>
-	for (key, log) in min_log {
-		full_log.insert(key.to_string(), ::lg::log::Log::new());
-		for entry in log {
-			let time = entry.time;
-			let data = entry.data;
-			let kind = entry.kind;
-			let note = entry.note;
-			full_log.get_mut(&key)
-				.unwrap()
-				.add(time, data, kind, note);
+fn options_end(args: &[String]) -> usize {
+	// Where global options stop being taken: at `--`, or at `complete`
+	// after the options before it, whose words are a command line to
+	// complete, options and all.
+	let mut index = 0;
+	while let Some(arg) = args.get(index) {
+		match arg.as_str() {
+			"--" | "complete" => return index,
+			arg if arg.starts_with("--") & arg.contains('=') => index += 1,
+			arg if arg.starts_with("--") => index += 2,
+			_ => break,
 		}
 	}
-	full_log
+	args.len()
+}
+
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>> {
+	// Pull a `--name value` or `--name=value` option out of the argument list
+	// before the terse grammar sees it; its `-` prefix would read as a till time.
+	let flag = format!("--{}", name);
+	let prefix = format!("--{}=", name);
+	let index = match args[..options_end(args)].iter().position(|arg| (arg == &flag) | arg.starts_with(&prefix)) {
+		Some(index) => index,
+		None => return Ok(None),
+	};
+	let arg = args.remove(index);
+	match arg.strip_prefix(&prefix) {
+		Some(value) => Ok(Some(value.to_string())),
+		None if index < args.len() => Ok(Some(args.remove(index))),
+		None => Err(LgError::Usage(format!("lg --{} VALUE ...", name))),
+	}
+}
+
+fn log_path(file_arg: Option<String>, config: &Config) -> Result<PathBuf> {
+	// Resolve the log file from the `--file` flag, then the `LG_FILE`
+	// variable, then the XDG data directory.
+	if let Some(path) = file_arg {
+		return Ok(PathBuf::from(path));
+	}
+	if let Some(path) = env::var_os("LG_FILE").filter(|path| !path.is_empty()) {
+		return Ok(PathBuf::from(path));
+	}
+	let data_home = match env::var_os("XDG_DATA_HOME").filter(|path| !path.is_empty()) {
+		Some(path) => PathBuf::from(path),
+		None => match env::var_os("HOME") {
+			Some(home) => Path::new(&home).join(".local").join("share"),
+			None => return Err(LgError::Refused("Cannot locate a log file; pass --file or set LG_FILE.".to_string())),
+		},
+	};
+	Ok(data_home.join("lg").join("log").with_extension(config.store.extension()))
+}
+
+fn process_kinds(args: &[&str], config: &mut Config) -> Result<()> {
+	// `lg kinds` lists the inference rules; the rest edit them in the config.
+	let rules = &mut config.kinds;
+	match args {
+		[] | ["list"] => {
+			for (data, kind) in &rules.aliases {
+				println!("{:<16} \t{}", data, kind);
+			}
+			for pattern in &rules.patterns {
+				let source = match pattern {
+					Pattern::Glob { glob, .. } => format!("glob {}", glob),
+					Pattern::Regex { regex, .. } => format!("regex {}", regex),
+				};
+				println!("{:<16} \t{}", source, pattern.kind());
+			}
+			if let Some(kind) = &rules.fallback {
+				println!("{:<16} \t{}", "(fallback)", kind);
+			}
+			return Ok(());
+		},
+		["add", "--regex", regex, kind] => {
+			let pattern = Pattern::Regex { regex: regex.to_string(), kind: kind.to_string() };
+			pattern.matches("")?;
+			rules.patterns.retain(|p| p.source() != *regex);
+			rules.patterns.push(pattern);
+		},
+		["add", data, kind] if data.contains(|c| "*?[".contains(c)) => {
+			let pattern = Pattern::Glob { glob: data.to_string(), kind: kind.to_string() };
+			pattern.matches("")?;
+			rules.patterns.retain(|p| p.source() != *data);
+			rules.patterns.push(pattern);
+		},
+		["add", data, kind] => {
+			rules.aliases.insert(data.to_string(), kind.to_string());
+		},
+		["remove", source] => {
+			if !rules.remove(source) {
+				return Err(LgError::Refused(format!("No kind rule for \"{}\".", source)));
+			}
+		},
+		["fallback"] => rules.fallback = None,
+		["fallback", kind] => rules.fallback = Some(kind.to_string()),
+		_ => return Err(LgError::Usage("lg kinds [list | add TASK KIND | add GLOB KIND | add --regex REGEX KIND | remove TASK | fallback [KIND]]".to_string())),
+	}
+	config.save(&Config::path())
 }
 
-fn record_log(file_path: &str, log_set: HashMap<String, &::lg::log::Log>) {
-	let mut min_log = HashMap::new();
-	for (key, log) in log_set {
-		min_log.insert(key, &log.vec);
+fn process_migrate(args: &[&str], file_path: &Path, config: &mut Config) -> Result<()> {
+	// `lg migrate --to sqlite|yaml` copies every profile and the journal
+	// into a sibling file of the other backend, leaving the original alone.
+	let target = match args {
+		["--to", name] => Backend::parse(name),
+		[arg] => arg.strip_prefix("--to=").and_then(Backend::parse),
+		_ => None,
+	};
+	let target = target.ok_or_else(|| LgError::Usage("lg migrate --to sqlite|yaml".to_string()))?;
+	if Backend::of(file_path) == target {
+		return Err(LgError::Refused(format!("Log is already stored as {}. {}", target.extension(), file_path.display())));
+	}
+	let target_path = file_path.with_extension(target.extension());
+	if target_path.exists() {
+		return Err(LgError::Refused(format!("Refusing to overwrite an existing log. {}", target_path.display())));
 	}
 
-	let file_path = std::path::Path::new(file_path);
-	let file = std::fs::File::create(file_path).expect("Log file not found.");
-	if let Err(_err) = serde_yaml::to_writer(file, &min_log) {
-		panic!("Log serialization failed.");
+	let log_set = store::copy(file_path, &target_path, config.backups)?;
+	let journal_path = Journal::path(file_path);
+	if journal_path.exists() {
+		std::fs::copy(&journal_path, Journal::path(&target_path)).map_err(LgError::io("Journal could not be copied."))?;
 	}
+	config.store = target;
+	config.save(&Config::path())?;
+	println!("Migrated {} profiles, {} entries, to {}",
+		log_set.len(), log_set.values().map(|log| log.vec.len()).sum::<usize>(), target_path.display());
+	println!("The default log is now log.{}; point --file or LG_FILE there if you use them.", target.extension());
+	Ok(())
+}
+
+fn process_restore(args: &[&str], file_path: &Path, backups: usize) -> Result<()> {
+	// `lg restore` lists the backups; `lg restore N` rolls back to one.
+	// The current log is itself rotated into `.1`, so a restore can be undone.
+	if Backend::of(file_path) != Backend::Yaml {
+		return Err(LgError::Refused(format!("Backups are kept for YAML logs only. {}", file_path.display())));
+	}
+	match args {
+		[] => {
+			for n in 1..=backups {
+				let path = backup_path(file_path, n);
+				if let Ok(modified) = std::fs::metadata(&path).and_then(|meta| meta.modified()) {
+					let modified = chrono::DateTime::<chrono::Utc>::from(modified).with_timezone(&lg::time::zone());
+					println!("{:>2}  {}  {}", n, lg::time::format_datetime(modified.fixed_offset()), path.display());
+				}
+			}
+		},
+		[n] => {
+			let path = match n.parse::<usize>() {
+				Ok(n) if n > 0 => backup_path(file_path, n),
+				_ => return Err(LgError::Usage("lg restore [N], numbered from 1".to_string())),
+			};
+			let contents = std::fs::read(&path)
+				.map_err(|err| LgError::Io(format!("Backup could not be read. {}", path.display()), err))?;
+			if let Err(err) = serde_yaml::from_slice::<HashMap<String, Vec<LogEntry>>>(&contents) {
+				return Err(LgError::Invalid(format!("Backup is not a valid log. {}", path.display()), err.to_string()));
+			}
+			write_atomic(file_path, &contents, backups)?;
+			println!("Restored {}", path.display());
+		},
+		_ => return Err(LgError::Usage("lg restore [N]".to_string())),
+	}
+	Ok(())
 }
 
 /*
@@ -219,40 +490,643 @@ fn new_test_log() -> Log {let ltr1 = "1".to_string();
 	log
 }*/
 
-/*
-	lg			# Alias for lg hr 24.
-	lg day		# Alias for lg day 28.
-	lg hr 24	# A day
-	lg day 28	# 4 weeks
-	lg week 24	# 6 months
-	lg day Feb4-Feb10	# 7 days. Assumes this year.
-	lg day 19Feb4-19Feb10
-	lg day 4-10
-	lg hr 14:37-15:00
-	lg hr :37-:00
-	lg hr 14-16
-*/
+fn profile_names(log_set: &HashMap<String, Log>) -> Vec<String> {
+	let mut names = log_set.keys().cloned().collect::<Vec<_>>();
+	names.sort();
+	names
+}
 
-fn main() {
-	let log_path = "/home/lemma/lglog.yml";
-	let mut log = read_log(log_path)
-		.remove("Lemma")
-		.unwrap();
-	match parse_commit_args(env::args().skip(1)
-									   .collect::<Vec<String>>()
-									   .iter()
-									   .map(AsRef::as_ref)
-									   .collect::<Vec<&str>>()) {
-		Some(cmd) => {
-			let cmd = process_command(cmd, &mut log);
-			for entry in cmd {
-				//println!("{:#?}", entry);
-				log.update(entry);
+fn active_profile(profile_arg: Option<String>, config: &Config, names: &[String]) -> Result<String> {
+	// `--profile`, then the configured default, then the file's only
+	// profile if it has just one. Named profiles must exist; only
+	// `lg profiles create` makes one, save "default" in an empty file.
+	match (profile_arg.or_else(|| config.profile.clone()), names) {
+		(Some(profile), _) if names.contains(&profile) => Ok(profile),
+		(Some(profile), _) => Err(LgError::NoProfile(profile, names.to_vec())),
+		(None, []) => Ok("default".to_string()),
+		(None, [only]) => Ok(only.to_string()),
+		(None, _) => Err(LgError::NoActiveProfile(names.to_vec())),
+	}
+}
+
+fn process_profiles(args: &[&str], log_set: &mut HashMap<String, Log>, active: &str, config: &mut Config) -> Result<bool> {
+	// Handle `lg profiles ...`, returning whether the log file changed.
+	Ok(match args {
+		[] | ["list"] => {
+			for name in profile_names(log_set) {
+				let marker = if name == active { "*" } else { " " };
+				println!("{} {} \t{} entries", marker, name, log_set[&name].vec.len());
+			}
+			false
+		},
+		["create", name] => {
+			if log_set.contains_key(*name) {
+				return Err(LgError::ProfileExists(name.to_string()));
+			}
+			log_set.insert(name.to_string(), Log::new());
+			true
+		},
+		["rename", from, to] => {
+			if log_set.contains_key(*to) {
+				return Err(LgError::ProfileExists(to.to_string()));
+			}
+			let names = profile_names(log_set);
+			let log = log_set.remove(*from).ok_or_else(|| LgError::NoProfile(from.to_string(), names))?;
+			log_set.insert(to.to_string(), log);
+			if config.profile.as_deref() == Some(*from) {
+				config.profile = Some(to.to_string());
+				config.save(&Config::path())?;
+			}
+			true
+		},
+		["delete", name] => {
+			let names = profile_names(log_set);
+			if log_set.remove(*name).is_none() {
+				return Err(LgError::NoProfile(name.to_string(), names));
+			}
+			if config.profile.as_deref() == Some(*name) {
+				config.profile = None;
+				config.save(&Config::path())?;
+			}
+			true
+		},
+		["default", name] => {
+			if !log_set.contains_key(*name) {
+				return Err(LgError::NoProfile(name.to_string(), profile_names(log_set)));
+			}
+			config.profile = Some(name.to_string());
+			config.save(&Config::path())?;
+			false
+		},
+		_ => return Err(LgError::Usage("lg profiles [list | create NAME | rename OLD NEW | delete NAME | default NAME]".to_string())),
+	})
+}
+
+// A journaled change: what was done, the entries it took out and those it put in.
+type Change = (Action, Vec<LogEntry>, Vec<LogEntry>);
+
+fn process_journal(args: &[&str], log_set: &mut HashMap<String, Log>, profile: &str, journal: &Journal) -> Result<Option<Change>> {
+	// Handle `lg undo`, `lg redo` and `lg history`, returning
+	// the change to store and journal, if any.
+	let (undo, redo) = journal.stacks(profile);
+	let (seq, action) = match args {
+		["history"] | ["history", _] => {
+			let count = match args.get(1) {
+				Some(n) => n.parse::<usize>().map_err(|_| LgError::Usage("lg history [N]".to_string()))?,
+				None => 10,
+			};
+			let records = journal.records.iter()
+				.filter(|r| r.profile == profile)
+				.collect::<Vec<_>>();
+			for record in records.iter().skip(records.len().saturating_sub(count)) {
+				let action = match record.action {
+					Action::Do => format!("lg {}", record.argv.join(" ")),
+					Action::Undo(seq) => format!("undo #{}", seq),
+					Action::Redo(seq) => format!("redo #{}", seq),
+				};
+				let state = match (record.action == Action::Do, undo.contains(&record.seq)) {
+					(true, false) => " (undone)",
+					_ => "",
+				};
+				println!("{:>4}  {}  {}  \t-{} +{}{}",
+					record.seq, lg::time::format_datetime(record.time), action,
+					record.before.len(), record.after.len(), state);
+			}
+			return Ok(None);
+		},
+		["undo"] => match undo.last() {
+			Some(&seq) => (seq, Action::Undo(seq)),
+			None => return Err(LgError::Refused("Nothing to undo.".to_string())),
+		},
+		["redo"] => match redo.last() {
+			Some(&seq) => (seq, Action::Redo(seq)),
+			None => return Err(LgError::Refused("Nothing to redo.".to_string())),
+		},
+		_ => return Err(LgError::Usage("lg undo | lg redo | lg history [N]".to_string())),
+	};
+
+	// Undo swaps a change's entries back; redo swaps them forward again.
+	let record = match journal.get(seq) {
+		Some(record) => record.clone(),
+		None => return Err(LgError::Conflict(format!("Journal entry #{} is missing.", seq))),
+	};
+	let (before, after) = match action {
+		Action::Undo(_) => (record.after, record.before),
+		_ => (record.before, record.after),
+	};
+	let log = log_set.entry(profile.to_string()).or_default();
+	if log.apply_diff(&before, &after).is_err() {
+		return Err(LgError::Conflict(format!("The log has changed since #{}; it can't be reverted cleanly.", seq)));
+	}
+	println!("{} #{}: lg {}", if let Action::Undo(_) = action { "Undid" } else { "Redid" }, seq, record.argv.join(" "));
+	Ok(Some((action, before, after)))
+}
+
+fn lock_log(file_path: &Path, exclusive: bool, timeout: Duration) -> Result<std::fs::File> {
+	// Take an advisory lock on `log.yml.lock` for the whole load-modify-save
+	// cycle. The log itself is replaced on every write, so it can't hold the lock.
+	// Writers lock exclusively and readers share; the lock drops with the file.
+	let mut lock_path = file_path.as_os_str().to_owned();
+	lock_path.push(".lock");
+	if let Some(dir) = file_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		std::fs::create_dir_all(dir).map_err(LgError::io("Log directory could not be created."))?;
+	}
+	let file = std::fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		.truncate(false)
+		.open(&lock_path)
+		.map_err(LgError::io("Log lock file could not be opened."))?;
+
+	let start = Instant::now();
+	loop {
+		let attempt = match exclusive {
+			true => file.try_lock(),
+			false => file.try_lock_shared(),
+		};
+		match attempt {
+			Ok(()) => return Ok(file),
+			Err(TryLockError::WouldBlock) if start.elapsed() < timeout =>
+				std::thread::sleep(Duration::from_millis(50)),
+			Err(TryLockError::WouldBlock) =>
+				return Err(LgError::Locked(format!("Log is in use by another lg process; gave up after {}s. {}",
+					timeout.as_secs(), lock_path.to_string_lossy()))),
+			Err(TryLockError::Error(err)) => return Err(LgError::Io("Log could not be locked.".to_string(), err)),
+		}
+	}
+}
+
+fn is_view(args: &[&str]) -> bool {
+	// `lg` and `lg _` only print.
+	matches!(args, [] | ["_"] | ["-h" | "--help", ..])
+}
+
+fn process_statement(file_path: &Path, profile_arg: Option<String>, config: &Config, clock: &dyn Clock, format: Format, exclusive: bool,
+	statement: impl FnOnce(&Log) -> Result<Option<RawStatement>>) -> Result<()> {
+	// Load the log, build a statement against it, then apply and journal
+	// whatever it changes. The log is loaded first since kinds are inferred from it.
+	let _lock = lock_log(file_path, exclusive, Duration::from_secs(config.lock_timeout))?;
+	let mut store = store::open(file_path, config.backups)?;
+	let mut log_set = store.load()?;
+	let profile = active_profile(profile_arg, config, &profile_names(&log_set))?;
+	let log = log_set.entry(profile.clone()).or_default();
+	let cmd = match statement(log)? {
+		Some(cmd) => cmd,
+		None => return Ok(()),
+	};
+	let cmd = process_command(cmd, log, clock, &config.window, format)?;
+	// Views change nothing; don't churn the backups over them.
+	if cmd.is_empty() {
+		return Ok(());
+	}
+	let before = log.vec.clone();
+	for entry in cmd {
+		//println!("{:#?}", entry);
+		log.update(entry)?;
+	}
+	let (removed, added) = log.diff(&before);
+	if removed.is_empty() & added.is_empty() {
+		return Ok(());
+	}
+	store.apply_updates(&profile, &removed, &added)?;
+	let argv = env::args().skip(1).collect();
+	load_journal(file_path)?.append(&profile, argv, Action::Do, removed, added)?;
+	Ok(())
+}
+
+fn load_journal(file_path: &Path) -> Result<Journal> {
+	// Unreadable lines are left out of undo and history; say so.
+	let journal = Journal::load(file_path)?;
+	for n in &journal.skipped {
+		eprintln!("lg: Journal line {} could not be read and was skipped. {}", n, Journal::path(file_path).display());
+	}
+	Ok(journal)
+}
+
+fn read_log(file_path: &Path, profile_arg: Option<String>, config: &Config) -> Result<Log> {
+	// Load the active profile's log for a command that only reads it.
+	let _lock = lock_log(file_path, false, Duration::from_secs(config.lock_timeout))?;
+	let mut log_set = store::open(file_path, config.backups)?.load()?;
+	let profile = active_profile(profile_arg, config, &profile_names(&log_set))?;
+	Ok(log_set.remove(&profile).unwrap_or_default())
+}
+
+fn read_range(file_path: &Path, profile_arg: Option<String>, config: &Config,
+	start: chrono::DateTime<chrono::FixedOffset>, end: chrono::DateTime<chrono::FixedOffset>) -> Result<Log> {
+	// Just the entries a view from `start` through `end` needs, read
+	// as narrowly as the backend allows.
+	let _lock = lock_log(file_path, false, Duration::from_secs(config.lock_timeout))?;
+	let store = store::open(file_path, config.backups)?;
+	let profile = active_profile(profile_arg, config, &store.profiles()?)?;
+	let mut log = Log::new();
+	log.vec = store.range(&profile, start, end)?;
+	Ok(log)
+}
+
+fn add_statement(matches: &clap::ArgMatches, rules: &KindRules, log: &Log) -> Result<Option<RawStatement>> {
+	// `lg add TASK --kind K --at T --till T NOTE`, spelled out.
+	let init = match (matches.is_present("retcon"), matches.value_of("at")) {
+		(false, None) => RawInit::Now,
+		(false, Some(at)) => RawInit::Time(at.to_string()),
+		(true, None) => RawInit::Retcon,
+		(true, Some(at)) => RawInit::RetconAt(at.to_string()),
+	};
+	let till = match (matches.value_of("till"), matches.value_of("for")) {
+		(Some(till), _) => RawTill::Till(till.to_string()),
+		(None, Some(span)) => RawTill::For(span.to_string()),
+		(None, None) => RawTill::Nil,
+	};
+	let note = matches.values_of("NOTE")
+		.map(|note| note.collect::<Vec<_>>().join(" "))
+		.unwrap_or_default();
+	let kind = matches.value_of("kind").map(str::to_string);
+	let data = matches.value_of("TASK").map(str::to_string);
+	let data = make_data(kind, data, note, &init, rules, log)?;
+	Ok(Some(RawStatement { init, till, data }))
+}
+
+fn edit_statement(matches: &clap::ArgMatches, rules: &KindRules, log: &Log, clock: &dyn Clock, window: &lg::time::Window) -> Result<Option<RawStatement>> {
+	// `lg edit TIME` rewrites the entry running at TIME, keeping
+	// whichever of its task, kind and note aren't given.
+	let time = lg::time::parse_start(matches.value_of("TIME").unwrap_or_default(), clock.now(), window)?;
+	let task = log.task_at(time);
+	if task.is_empty() {
+		return Err(LgError::NoEntry(time));
+	}
+	let start = task.time.to_rfc3339();
+	if matches.is_present("delete") {
+		let data = ValidData { kind: "".to_string(), data: "".to_string(), note: "".to_string() };
+		return Ok(Some(RawStatement { init: RawInit::Time(start), till: RawTill::Nil, data: Some(data) }));
+	}
+	let till = match (matches.value_of("till"), matches.value_of("for")) {
+		(Some(till), _) => RawTill::Till(till.to_string()),
+		(None, Some(span)) => RawTill::For(span.to_string()),
+		(None, None) => RawTill::Nil,
+	};
+	let init = RawInit::RetconAt(start);
+	let (data, kind, note) = (matches.value_of("task"), matches.value_of("kind"), matches.value_of("note"));
+	if data.is_none() & kind.is_none() & note.is_none() {
+		return Ok(Some(RawStatement { init, till, data: None }));
+	}
+	// A new task name with no kind is inferred afresh.
+	let kind = match (kind, data) {
+		(Some(kind), _) => Some(kind.to_string()),
+		(None, None) => Some(task.kind.clone()),
+		(None, Some(_)) => None,
+	};
+	let data = Some(data.unwrap_or(&task.data).to_string());
+	let note = note.unwrap_or(&task.note).to_string();
+	let data = make_data(kind, data, note, &init, rules, log)?;
+	Ok(Some(RawStatement { init, till, data }))
+}
+
+fn process_config(matches: &clap::ArgMatches, config: &mut Config) -> Result<()> {
+	// `lg config` shows the settings; `set` and `unset` edit them by
+	// their YAML key, so every field is reachable without a flag of its own.
+	use serde_yaml::Value;
+	let invalid = |err: serde_yaml::Error| LgError::Invalid("Config is invalid.".to_string(), err.to_string());
+	let mut value = serde_yaml::to_value(&*config).map_err(invalid)?;
+	let key = matches.subcommand().1.and_then(|m| m.value_of("KEY")).unwrap_or_default();
+	let mut path = key.split('.').collect::<Vec<_>>();
+	let last = path.pop().unwrap_or_default();
+	let no_setting = || LgError::Refused(format!("No such setting \"{}\".", key));
+	let mut parent = &mut value;
+	for part in path {
+		// Maps left out while empty, like `colors`, are started by `set`.
+		if (matches.subcommand_name() == Some("set")) & parent.get(part).is_none() {
+			if let Some(map) = parent.as_mapping_mut() {
+				map.insert(Value::String(part.to_string()), Value::Mapping(Default::default()));
+			}
+		}
+		parent = parent.get_mut(part).ok_or_else(no_setting)?;
+	}
+	match matches.subcommand() {
+		("path", _) => println!("{}", Config::path().display()),
+		("get", _) => {
+			let setting = parent.get(last).ok_or_else(no_setting)?;
+			println!("{}", serde_yaml::to_string(setting).map_err(invalid)?.trim_start_matches("---\n").trim_end());
+		},
+		(action @ "set", Some(m)) | (action @ "unset", Some(m)) => {
+			let parent = parent.as_mapping_mut().ok_or_else(no_setting)?;
+			match action {
+				"set" => {
+					let setting = serde_yaml::from_str::<Value>(m.value_of("VALUE").unwrap_or_default()).map_err(invalid)?;
+					parent.insert(Value::String(last.to_string()), setting);
+				},
+				_ => {
+					parent.remove(&Value::String(last.to_string()));
+				},
+			}
+			let updated: Config = serde_yaml::from_value(value).map_err(invalid)?;
+			// Keys the config doesn't have are dropped on the way through.
+			if action == "set" {
+				let kept = serde_yaml::to_value(&updated).map_err(invalid)?;
+				key.split('.').try_fold(&kept, |setting, part| setting.get(part)).ok_or_else(no_setting)?;
+			}
+			// Check what only parses at use.
+			lg::time::init_zone(updated.timezone.as_deref())?;
+			updated.window.spans()?;
+			lg::color::set_color(updated.color, &updated.colors)?;
+			lg::views::set_period(&updated.period)?;
+			*config = updated;
+			config.save(&Config::path())?;
+		},
+		_ => println!("{}", serde_yaml::to_string(&*config).map_err(invalid)?.trim_start_matches("---\n").trim_end()),
+	}
+	Ok(())
+}
+
+fn canonical_args(matches: &clap::ArgMatches) -> Vec<String> {
+	// Spell a parsed subcommand back out as the arguments
+	// the `process_*` handlers match on.
+	let (name, matches) = matches.subcommand();
+	let mut args = vec![name.to_string()];
+	let matches = match matches {
+		Some(matches) => matches,
+		None => return args,
+	};
+	let values = |matches: &clap::ArgMatches, keys: &[&str]| keys.iter()
+		.filter_map(|key| matches.value_of(key))
+		.map(str::to_string)
+		.collect::<Vec<_>>();
+	match name {
+		"profiles" | "kinds" => if let (sub, Some(matches)) = matches.subcommand() {
+			args.push(sub.to_string());
+			if matches.is_present("regex") {
+				args.push("--regex".to_string());
 			}
-			let mut log_map = HashMap::new();
-			log_map.insert("Lemma".to_string(), &log);
-			record_log(log_path, log_map);
+			args.extend(values(matches, &["NAME", "OLD", "NEW", "PATTERN", "KIND"]));
 		},
-		None => (),
+		"history" | "restore" => args.extend(values(matches, &["N"])),
+		"migrate" => args.extend(vec!["--to".to_string(), matches.value_of("to").unwrap_or_default().to_string()]),
+		_ => (),
+	}
+	args
+}
+
+fn complete_words(words: &[&str], names: impl Fn(&[&str]) -> Vec<String>, log: &Log, rules: &KindRules, now: chrono::DateTime<chrono::FixedOffset>) -> Vec<String> {
+	// Candidates for the last of `words`, the one under the cursor.
+	// Subcommands and their options complete by name; anything else by
+	// the terse grammar, from the log and the kind rules.
+	let word = words.last().copied().unwrap_or_default();
+	let candidates = match words {
+		[] | [_] => names(&[]).into_iter()
+			.filter(|name| name != "complete")
+			.chain(lg::completions::candidates(word, log, rules, now))
+			.collect(),
+		["completions", _] => vec!["bash".to_string(), "zsh".to_string(), "fish".to_string()],
+		[.., "-k" | "--kind", _] => lg::completions::kinds(log, rules),
+		[.., "--task", _] => lg::completions::tasks(log, 50),
+		[.., "-a" | "--at" | "-t" | "--till" | "--now", _] => lg::completions::boundaries(log, now),
+		[sub, _] if !names(&[sub]).is_empty() => names(&[sub]),
+		["add", ..] => lg::completions::tasks(log, 50),
+		[sub, ..] if names(&[]).iter().any(|name| name == sub) => Vec::new(),
+		_ => return lg::completions::candidates(word, log, rules, now),
+	};
+	candidates.into_iter()
+		.filter(|candidate| candidate.starts_with(word))
+		.collect()
+}
+
+fn run() -> Result<()> {
+	let mut args = env::args().skip(1).collect::<Vec<String>>();
+	let mut config = Config::load(&Config::path())?;
+	lg::time::init_zone(config.timezone.as_deref())?;
+	lg::time::set_hour_format(config.hour_format);
+	config.window.spans()?;
+	let log_path = log_path(take_option(&mut args, "file")?, &config)?;
+	let profile_arg = take_option(&mut args, "profile")?;
+	// `--color` overrides the config's.
+	let color = match take_option(&mut args, "color")? {
+		Some(mode) => lg::color::ColorMode::parse(&mode)?,
+		None => config.color,
+	};
+	lg::color::set_color(color, &config.colors)?;
+	// `--width` and `--period` override the terminal's and the config's.
+	let width = match take_option(&mut args, "width")? {
+		Some(width) => Some(width.parse::<usize>().map_err(|_| LgError::Usage("lg --width COLUMNS ...".to_string()))?),
+		None => None,
+	};
+	lg::views::set_width(width);
+	lg::views::set_period(&config.period)?;
+	if let Some(period) = take_option(&mut args, "period")? {
+		lg::views::set_period(&period).map_err(|_| LgError::Usage("lg --period 15m|30m|1h|2h|1d ...".to_string()))?;
+	}
+	let format = match take_option(&mut args, "format")? {
+		Some(format) => Format::parse(&format)?,
+		None => Format::Text,
+	};
+	// `--now` runs the command as if at another moment.
+	let clock: Box<dyn Clock> = match take_option(&mut args, "now")? {
+		Some(now) => Box::new(FixedClock(lg::time::parse_start(&now, SystemClock.now(), &config.window)?)),
+		None => Box::new(SystemClock),
+	};
+	let clock = clock.as_ref();
+	let lock_timeout = Duration::from_secs(config.lock_timeout);
+	let mut args = args.iter()
+		.map(AsRef::as_ref)
+		.collect::<Vec<&str>>();
+
+	// A leading subcommand name goes through the cli.yml parser;
+	// anything else is the terse grammar.
+	let yaml = clap::load_yaml!("cli.yml");
+	let subcommand_names = |path: &[&str]| {
+		// The subcommands nested under `path`, e.g. `["kinds"]`.
+		let mut node = yaml;
+		for name in path {
+			match node["subcommands"].as_vec().into_iter().flatten().map(|sub| &sub[*name]).find(|sub| !sub.is_badvalue()) {
+				Some(sub) => node = sub,
+				None => return Vec::new(),
+			}
+		}
+		node["subcommands"].as_vec().into_iter().flatten()
+			.filter_map(|sub| sub.as_hash())
+			.flat_map(|sub| sub.keys())
+			.filter_map(|name| name.as_str())
+			.collect::<Vec<_>>()
+	};
+	let subcommands = subcommand_names(&[]).into_iter()
+		.chain(Some("help"))
+		.collect::<Vec<_>>();
+	let canonical;
+	if args.first().is_some_and(|arg| subcommands.contains(arg)) {
+		let matches = match clap::App::from_yaml(yaml).version(clap::crate_version!()).get_matches_from_safe(Some("lg").into_iter().chain(args.iter().copied())) {
+			Ok(matches) => matches,
+			Err(err) => match err.kind {
+				clap::ErrorKind::HelpDisplayed | clap::ErrorKind::VersionDisplayed => {
+					println!("{}", err.message);
+					return Ok(());
+				},
+				_ => return Err(LgError::Arguments(err.message)),
+			},
+		};
+		match matches.subcommand() {
+			("add", Some(matches)) => return process_statement(&log_path, profile_arg, &config, clock, format, true,
+				|log| add_statement(matches, &config.kinds, log)),
+			("view", Some(matches)) => return process_statement(&log_path, profile_arg, &config, clock, format, false,
+				|_| Ok(Some(RawStatement {
+					init: if matches.is_present("current") { RawInit::Retcon } else { RawInit::Now },
+					till: RawTill::Nil,
+					data: None,
+				}))),
+			("edit", Some(matches)) => return process_statement(&log_path, profile_arg, &config, clock, format, true,
+				|log| edit_statement(matches, &config.kinds, log, clock, &config.window)),
+			("config", Some(matches)) => return process_config(matches, &mut config),
+			("report", Some(matches)) => {
+				let now = clock.now();
+				let (start, end) = lg::views::parse_range(matches.value_of("RANGE"), now, &config.window)?;
+				let by = matches.value_of("by").and_then(By::parse);
+				let log = read_range(&log_path, profile_arg, &config, start, end)?;
+				// What hasn't happened yet isn't counted.
+				let report = Report::new(&log.slice(start, end.min(now).max(start)), by);
+				match format {
+					Format::Text => println!("{}", report),
+					_ => lg::format::print(format, &report.rows()),
+				}
+				return Ok(());
+			},
+			("list", Some(matches)) => {
+				let now = clock.now();
+				let (start, end) = lg::views::parse_range(matches.value_of("RANGE"), now, &config.window)?;
+				let log = read_range(&log_path, profile_arg, &config, start, end)?;
+				let listing = Listing::new(&log, start, end, now);
+				match format {
+					Format::Text => println!("{}", listing),
+					_ => lg::format::print(format, &listing.items),
+				}
+				return Ok(());
+			},
+			(name @ ("hr" | "day" | "week"), Some(matches)) => {
+				let span = Span::parse(name).expect("Unknown view!");
+				let (start, end) = span.range(matches.value_of("RANGE"), clock.now(), &config.window)?;
+				let log = read_range(&log_path, profile_arg, &config, start, end)?;
+				match format {
+					Format::Text => println!("{}", span.draw(&log, start, end)),
+					_ => lg::format::print(format, &Listing::new(&log, start, end, clock.now()).items),
+				}
+				return Ok(());
+			},
+			("completions", Some(matches)) => {
+				print!("{}", lg::completions::script(matches.value_of("SHELL").unwrap_or_default()).unwrap_or_default());
+				return Ok(());
+			},
+			("complete", Some(matches)) => {
+				let words = matches.values_of("WORDS").map(Iterator::collect::<Vec<_>>).unwrap_or_default();
+				// Completion carries on without the log, e.g. with no profile chosen.
+				let log = read_log(&log_path, profile_arg, &config).unwrap_or_default();
+				let names = |path: &[&str]| subcommand_names(path).into_iter().map(str::to_string).collect();
+				for candidate in complete_words(&words, names, &log, &config.kinds, clock.now()) {
+					println!("{}", candidate);
+				}
+				return Ok(());
+			},
+			_ => canonical = canonical_args(&matches),
+		}
+		args = canonical.iter()
+			.map(AsRef::as_ref)
+			.collect();
+	}
+
+	if let ["profiles", rest @ ..] = args.as_slice() {
+		let _lock = lock_log(&log_path, !matches!(rest, [] | ["list"]), lock_timeout)?;
+		let mut store = store::open(&log_path, config.backups)?;
+		let mut log_set = store.load()?;
+		// Profiles can be listed and managed with none of them chosen.
+		let profile = active_profile(profile_arg, &config, &profile_names(&log_set)).unwrap_or_default();
+		if process_profiles(rest, &mut log_set, &profile, &mut config)? {
+			store.save(&log_set)?;
+		}
+		return Ok(());
+	}
+
+	if let ["restore", rest @ ..] = args.as_slice() {
+		let _lock = lock_log(&log_path, !rest.is_empty(), lock_timeout)?;
+		return process_restore(rest, &log_path, config.backups);
+	}
+
+	if let ["kinds", rest @ ..] = args.as_slice() {
+		return process_kinds(rest, &mut config);
+	}
+
+	if let ["migrate", rest @ ..] = args.as_slice() {
+		let _lock = lock_log(&log_path, false, lock_timeout)?;
+		return process_migrate(rest, &log_path, &mut config);
+	}
+
+	if let ["undo" | "redo" | "history", ..] = args.as_slice() {
+		let _lock = lock_log(&log_path, args[0] != "history", lock_timeout)?;
+		let mut store = store::open(&log_path, config.backups)?;
+		let mut log_set = store.load()?;
+		let profile = active_profile(profile_arg, &config, &profile_names(&log_set))?;
+		// The journal is appended only after the log itself is safely written.
+		let mut journal = load_journal(&log_path)?;
+		if let Some((action, removed, added)) = process_journal(&args, &mut log_set, &profile, &journal)? {
+			store.apply_updates(&profile, &removed, &added)?;
+			let argv = env::args().skip(1).collect();
+			journal.append(&profile, argv, action, removed, added)?;
+		}
+		return Ok(());
+	}
+
+	process_statement(&log_path, profile_arg, &config, clock, format, !is_view(&args),
+		|log| parse_commit_args(args, &config.kinds, log, clock))
+}
+
+fn main() {
+	if let Err(err) = run() {
+		eprintln!("lg: {}", err);
+		std::process::exit(err.exit_code());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn at(time: &str) -> chrono::DateTime<chrono::FixedOffset> {
+		lg::time::set_zone(chrono_tz::America::Los_Angeles);
+		chrono::DateTime::parse_from_rfc3339(time).unwrap()
+	}
+
+	fn args(line: &str) -> Vec<String> {
+		line.split(' ').map(str::to_string).collect()
+	}
+
+	#[test]
+	fn take_option_leaves_the_words_to_complete() {
+		let mut line = args("--file log.yml complete -- --now");
+		assert_eq!(take_option(&mut line, "file").unwrap().as_deref(), Some("log.yml"));
+		assert_eq!(take_option(&mut line, "now").unwrap(), None);
+		assert_eq!(line, args("complete -- --now"));
+		// Elsewhere options are taken wherever they are.
+		let mut line = args("Coding :Work --now 9:00");
+		assert_eq!(take_option(&mut line, "now").unwrap().as_deref(), Some("9:00"));
+		assert_eq!(line, args("Coding :Work"));
+	}
+
+	#[test]
+	fn completes_times_after_now() {
+		let now = at("2026-10-18T11:20:00-07:00");
+		let mut log = Log::new();
+		log.add(at("2026-10-18T10:30:00-07:00"), "Coding".to_string(), "Work".to_string(), "".to_string());
+		log.add(at("2026-10-18T11:00:00-07:00"), "Reading".to_string(), "Leisure".to_string(), "".to_string());
+		let mut line = args("complete -- --now");
+		line.push(String::new());
+		assert_eq!(take_option(&mut line, "now").unwrap(), None);
+		let words = line[2..].iter().map(String::as_str).collect::<Vec<_>>();
+		let names = |_: &[&str]| vec!["add".to_string(), "list".to_string()];
+		let candidates = complete_words(&words, names, &log, &KindRules::default(), now);
+		assert_eq!(candidates, vec!["11:20", "11:00", "10:30"]);
+	}
+
+	#[test]
+	fn config_set_refuses_unknown_keys() {
+		let yaml = clap::load_yaml!("cli.yml");
+		for key in ["bogus", "window.bogus"] {
+			let matches = clap::App::from_yaml(yaml).get_matches_from(["lg", "config", "set", key, "1"]);
+			let result = process_config(matches.subcommand_matches("config").unwrap(), &mut Config::default());
+			assert!(matches!(result, Err(LgError::Refused(_))), "{}", key);
+		}
 	}
 }
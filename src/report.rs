@@ -0,0 +1,236 @@
+// //// Report //// //
+use std::fmt;
+use chrono::prelude::*;
+use chrono::Duration;
+use super::log::{LogEntry, LogSlice};
+use super::display_width;
+use super::format;
+use super::time::format_datetime;
+
+// Time spent per kind over a slice of the log, for "how long did I
+// spend on Work this week". The first entry is already clipped to the
+// slice's start; the last is clipped to its end.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum By {
+	Data,
+	Note,
+}
+
+impl By {
+	pub fn parse(name: &str) -> Option<By> {
+		match name {
+			"data" | "task" => Some(By::Data),
+			"note" => Some(By::Note),
+			_ => None,
+		}
+	}
+
+	fn key(self, entry: &LogEntry) -> &str {
+		match self {
+			By::Data => &entry.data,
+			By::Note => &entry.note,
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Total {
+	pub name: String,
+	pub duration: Duration,
+	// The kind's time split by task or note, when asked for.
+	pub parts: Vec<Total>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+	pub start: DateTime<FixedOffset>,
+	pub end: DateTime<FixedOffset>,
+	pub totals: Vec<Total>,
+	pub untracked: Duration,
+}
+
+// A report as records: a row per kind, then per part of each kind,
+// then one for the untracked time, which has no kind.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Row<'a> {
+	pub start: DateTime<FixedOffset>,
+	pub end: DateTime<FixedOffset>,
+	pub kind: Option<&'a str>,
+	pub part: Option<&'a str>,
+	#[serde(serialize_with = "format::seconds")]
+	pub duration: Duration,
+	// Percent of the tracked time.
+	pub share: Option<f64>,
+}
+
+impl format::Record for Row<'_> {
+	const COLUMNS: &'static [&'static str] = &["start", "end", "kind", "part", "duration", "share"];
+}
+
+fn add_to<'a>(totals: &'a mut Vec<Total>, name: &str, duration: Duration) -> &'a mut Total {
+	let index = match totals.iter().position(|total| total.name == name) {
+		Some(index) => index,
+		None => {
+			totals.push(Total { name: name.to_string(), duration: Duration::zero(), parts: Vec::new() });
+			totals.len() - 1
+		},
+	};
+	let total = &mut totals[index];
+	total.duration += duration;
+	total
+}
+
+fn sort(totals: &mut [Total]) {
+	// Longest first, then by name.
+	totals.sort_by(|a, b| b.duration.cmp(&a.duration).then_with(|| a.name.cmp(&b.name)));
+	for total in totals {
+		sort(&mut total.parts);
+	}
+}
+
+impl Report {
+	pub fn new(slice: &LogSlice, by: Option<By>) -> Report {
+		let mut totals = Vec::<Total>::new();
+		let mut untracked = Duration::zero();
+		let entries = slice.iter().collect::<Vec<_>>();
+		for (i, entry) in entries.iter().enumerate() {
+			let end = entries.get(i + 1)
+				.map_or(slice.end_bound, |next| next.time)
+				.min(slice.end_bound);
+			let duration = end.signed_duration_since(entry.time);
+			if duration <= Duration::zero() {
+				continue;
+			}
+			if entry.is_nil() | entry.kind.is_empty() {
+				untracked += duration;
+				continue;
+			}
+			let total = add_to(&mut totals, &entry.kind, duration);
+			if let Some(by) = by {
+				add_to(&mut total.parts, by.key(entry), duration);
+			}
+		}
+		sort(&mut totals);
+		Report { start: slice.start_bound, end: slice.end_bound, totals, untracked }
+	}
+
+	pub fn tracked(&self) -> Duration {
+		self.totals.iter().fold(Duration::zero(), |sum, total| sum + total.duration)
+	}
+
+	pub fn rows(&self) -> Vec<Row<'_>> {
+		let tracked = self.tracked();
+		let row = |kind, part, duration: Duration| Row {
+			start: self.start,
+			end: self.end,
+			kind,
+			part,
+			duration,
+			share: kind.map(|_| percent(duration, tracked)),
+		};
+		let mut rows = Vec::new();
+		for total in &self.totals {
+			rows.push(row(Some(total.name.as_str()), None, total.duration));
+			for part in &total.parts {
+				rows.push(row(Some(total.name.as_str()), Some(part.name.as_str()), part.duration));
+			}
+		}
+		rows.push(row(None, None, self.untracked));
+		rows
+	}
+}
+
+fn percent(duration: Duration, tracked: Duration) -> f64 {
+	match tracked.num_seconds() {
+		0 => 0.0,
+		secs => 100.0 * duration.num_seconds() as f64 / secs as f64,
+	}
+}
+
+fn hours(duration: Duration) -> f64 {
+	duration.num_seconds() as f64 / 3600.0
+}
+
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Kinds, indented parts under them, then the tracked and
+		// untracked sums. Percentages are of the tracked time.
+		let tracked = self.tracked();
+		let percent = |duration: Duration| percent(duration, tracked);
+		let part_name = |name: &str| match name {
+			"" => "(none)".to_string(),
+			name => name.to_string(),
+		};
+		let width = self.totals.iter()
+			.flat_map(|total| Some(display_width(&total.name))
+				.into_iter()
+				.chain(total.parts.iter().map(|part| 2 + display_width(&part_name(&part.name)))))
+			.chain(Some("Tracked".len()))
+			.max()
+			.unwrap_or_default();
+		let pad = |name: String| format!("{}{}", name, " ".repeat(width - display_width(&name)));
+
+		writeln!(f, "{} - {}", format_datetime(self.start), format_datetime(self.end))?;
+		for total in &self.totals {
+			writeln!(f, "{}  {:>7.2}h  {:>5.1}%", pad(total.name.clone()), hours(total.duration), percent(total.duration))?;
+			for part in &total.parts {
+				writeln!(f, "{}  {:>7.2}h  {:>5.1}%", pad(format!("  {}", part_name(&part.name))), hours(part.duration), percent(part.duration))?;
+			}
+		}
+		writeln!(f, "{}  {:>7.2}h", pad("Tracked".to_string()), hours(tracked))?;
+		write!(f, "{}  {:>7.2}h", pad("∅".to_string()), hours(self.untracked))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::log::Log;
+	use crate::tests::at;
+
+	fn log() -> Log {
+		let mut log = Log::new();
+		log.vec = vec![
+			LogEntry::new(at("2026-10-18T08:00:00-07:00"), "Work", "A", "x"),
+			LogEntry::new(at("2026-10-18T09:00:00-07:00"), "Meal", "lunch", ""),
+			LogEntry::nil(at("2026-10-18T09:30:00-07:00")),
+			LogEntry::new(at("2026-10-18T10:00:00-07:00"), "Work", "B", ""),
+			LogEntry::new(at("2026-10-18T10:30:00-07:00"), "Work", "A", "y"),
+			LogEntry::new(at("2026-10-18T12:00:00-07:00"), "Leisure", "R", ""),
+		];
+		log
+	}
+
+	fn totals(totals: &[Total]) -> Vec<(&str, i64)> {
+		totals.iter().map(|total| (total.name.as_str(), total.duration.num_minutes())).collect()
+	}
+
+	#[test]
+	fn totals_are_clipped_to_the_slice_and_sorted() {
+		// From before the first entry, so opening untracked, to partway
+		// through the last.
+		let log = log();
+		let report = Report::new(&log.slice(at("2026-10-18T07:30:00-07:00"), at("2026-10-18T12:30:00-07:00")), None);
+		assert_eq!(totals(&report.totals), vec![("Work", 180), ("Leisure", 30), ("Meal", 30)]);
+		assert_eq!(report.untracked, Duration::minutes(60));
+		assert_eq!(report.tracked(), Duration::minutes(240));
+		let shares = report.rows().iter().map(|row| (row.kind, row.share)).collect::<Vec<_>>();
+		assert_eq!(shares, vec![(Some("Work"), Some(75.0)), (Some("Leisure"), Some(12.5)), (Some("Meal"), Some(12.5)), (None, None)]);
+		// Starting partway through an entry counts only the rest of it.
+		let report = Report::new(&log.slice(at("2026-10-18T08:45:00-07:00"), at("2026-10-18T09:15:00-07:00")), None);
+		assert_eq!(totals(&report.totals), vec![("Meal", 15), ("Work", 15)]);
+		assert_eq!(report.untracked, Duration::zero());
+	}
+
+	#[test]
+	fn totals_split_by_data_or_note() {
+		let log = log();
+		let slice = log.slice(at("2026-10-18T07:30:00-07:00"), at("2026-10-18T12:30:00-07:00"));
+		let report = Report::new(&slice, Some(By::Data));
+		assert_eq!(totals(&report.totals[0].parts), vec![("A", 150), ("B", 30)]);
+		let report = Report::new(&slice, Some(By::Note));
+		assert_eq!(totals(&report.totals[0].parts), vec![("y", 90), ("x", 60), ("", 30)]);
+		assert_eq!(totals(&report.totals[2].parts), vec![("", 30)]);
+	}
+}
@@ -0,0 +1,423 @@
+// //// Store //// //
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use chrono::prelude::*;
+use super::log::{Log, LogEntry};
+use super::error::{LgError, Result};
+
+// Where the log lives between runs. Commands load every profile in
+// full, but the range views read only their span, and writes touch
+// only their entries, as narrowly as the backend allows.
+pub trait LogStore {
+	// Every profile, keyed by name.
+	fn load(&self) -> Result<HashMap<String, Log>>;
+	// The profiles' names, sorted.
+	fn profiles(&self) -> Result<Vec<String>>;
+	// Replace the stored profiles with `log_set`.
+	fn save(&mut self, log_set: &HashMap<String, Log>) -> Result<()>;
+	// A profile's entries from the one running at `start` through `end`,
+	// and the first after that, so the last in range has its end.
+	fn range(&self, profile: &str, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> Result<Vec<LogEntry>>;
+	// Take `removed` out of a profile and put `added` in, as `Log::apply_diff`.
+	fn apply_updates(&mut self, profile: &str, removed: &[LogEntry], added: &[LogEntry]) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+	Yaml,
+	Sqlite,
+}
+
+impl Backend {
+	pub fn of(path: &Path) -> Backend {
+		match path.extension().and_then(|ext| ext.to_str()) {
+			Some("sqlite") | Some("sqlite3") | Some("db") => Backend::Sqlite,
+			_ => Backend::Yaml,
+		}
+	}
+
+	pub fn parse(name: &str) -> Option<Backend> {
+		match name {
+			"yaml" | "yml" => Some(Backend::Yaml),
+			"sqlite" => Some(Backend::Sqlite),
+			_ => None,
+		}
+	}
+
+	pub fn extension(&self) -> &'static str {
+		match self {
+			Backend::Yaml => "yml",
+			Backend::Sqlite => "sqlite",
+		}
+	}
+}
+
+pub fn open(path: &Path, backups: usize) -> Result<Box<dyn LogStore>> {
+	// The file extension picks the backend.
+	Ok(match Backend::of(path) {
+		Backend::Yaml => Box::new(YamlStore { path: path.to_path_buf(), backups }),
+		Backend::Sqlite => Box::new(SqliteStore::open(path)?),
+	})
+}
+
+pub fn copy(from: &Path, to: &Path, backups: usize) -> Result<HashMap<String, Log>> {
+	// Every profile of one store saved into another, as `lg migrate` does.
+	let log_set = open(from, 0)?.load()?;
+	open(to, backups)?.save(&log_set)?;
+	Ok(log_set)
+}
+
+// //// YAML //// //
+
+// The whole history as one human-editable file, keyed by profile.
+pub struct YamlStore {
+	path: PathBuf,
+	backups: usize,
+}
+
+impl LogStore for YamlStore {
+	fn load(&self) -> Result<HashMap<String, Log>> {
+		// Serde load; ensure correctly sorted
+		// A log that doesn't exist yet is simply empty.
+		let file = match std::fs::File::open(&self.path) {
+			Ok(file) => file,
+			Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+			Err(err) => return Err(LgError::Io("Log file could not be opened.".to_string(), err)),
+		};
+		let min_log: HashMap<String, Vec<LogEntry>> = serde_yaml::from_reader(file)
+			.map_err(|err| LgError::Invalid("Log file is invalid.".to_string(), err.to_string()))?;
+		//load minlog
+		let mut full_log = HashMap::new();//HashMap<String, Log>
+		for (key, log) in min_log {
+			full_log.insert(key.to_string(), Log::new());
+			for entry in log {
+				let time = entry.time;
+				let data = entry.data;
+				let kind = entry.kind;
+				let note = entry.note;
+				full_log.get_mut(&key)
+					.unwrap()
+					.add(time, data, kind, note);
+			}
+		}
+		Ok(full_log)
+	}
+
+	fn save(&mut self, log_set: &HashMap<String, Log>) -> Result<()> {
+		// Every profile is written back, in a stable order.
+		let mut min_log = BTreeMap::new();
+		for (key, log) in log_set {
+			min_log.insert(key, &log.vec);
+		}
+
+		let contents = serde_yaml::to_string(&min_log)
+			.map_err(|err| LgError::Invalid("Log serialization failed.".to_string(), err.to_string()))?;
+		write_atomic(&self.path, contents.as_bytes(), self.backups)
+	}
+
+	fn profiles(&self) -> Result<Vec<String>> {
+		let mut names = self.load()?.into_keys().collect::<Vec<_>>();
+		names.sort();
+		Ok(names)
+	}
+
+	fn range(&self, profile: &str, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> Result<Vec<LogEntry>> {
+		Ok(match self.load()?.get(profile) {
+			Some(log) => log.iter_range(start, end)
+				.chain(log.iter().find(|entry| entry.time > end))
+				.cloned()
+				.collect(),
+			None => Vec::new(),
+		})
+	}
+
+	fn apply_updates(&mut self, profile: &str, removed: &[LogEntry], added: &[LogEntry]) -> Result<()> {
+		// YAML has no partial writes; rewrite the file.
+		let mut log_set = self.load()?;
+		log_set.entry(profile.to_string()).or_default().apply_diff(removed, added)?;
+		self.save(&log_set)
+	}
+}
+
+pub fn backup_path(file_path: &Path, n: usize) -> PathBuf {
+	let mut path = file_path.as_os_str().to_owned();
+	path.push(format!(".{}", n));
+	PathBuf::from(path)
+}
+
+fn rotate_backups(file_path: &Path, backups: usize) -> Result<()> {
+	// Shift `log.yml.1` .. `log.yml.N-1` up by one, dropping the oldest,
+	// then copy the live file into `log.yml.1`.
+	if (backups == 0) | !file_path.exists() {
+		return Ok(());
+	}
+	for n in (1..backups).rev() {
+		let from = backup_path(file_path, n);
+		if from.exists() {
+			std::fs::rename(&from, backup_path(file_path, n + 1)).map_err(LgError::io("Log backup could not be rotated."))?;
+		}
+	}
+	std::fs::copy(file_path, backup_path(file_path, 1)).map_err(LgError::io("Log backup could not be written."))?;
+	Ok(())
+}
+
+pub fn write_atomic(file_path: &Path, contents: &[u8], backups: usize) -> Result<()> {
+	// Write beside the log, flush to disk, then rename over it, so a crash
+	// leaves either the old file or the new one but never half of either.
+	use std::io::Write;
+	let dir = match file_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+		Some(dir) => dir,
+		None => Path::new("."),
+	};
+	std::fs::create_dir_all(dir).map_err(LgError::io("Log directory could not be created."))?;
+	let mut tmp_path = file_path.as_os_str().to_owned();
+	tmp_path.push(format!(".tmp{}", std::process::id()));
+	let tmp_path = PathBuf::from(tmp_path);
+
+	let mut file = std::fs::File::create(&tmp_path).map_err(LgError::io("Log file could not be created."))?;
+	if let Err(err) = file.write_all(contents).and_then(|_| file.sync_all()) {
+		let _ = std::fs::remove_file(&tmp_path);
+		return Err(LgError::Io("Log file could not be written.".to_string(), err));
+	}
+	rotate_backups(file_path, backups)?;
+	std::fs::rename(&tmp_path, file_path).map_err(LgError::io("Log file could not be replaced."))?;
+	// Persist the rename itself.
+	if let Ok(dir) = std::fs::File::open(dir) {
+		let _ = dir.sync_all();
+	}
+	Ok(())
+}
+
+// //// SQLite //// //
+
+// One row per entry, indexed by profile and UTC timestamp, so a change
+// touches only its own rows. Transactions stand in for backups here.
+pub struct SqliteStore {
+	conn: rusqlite::Connection,
+}
+
+impl SqliteStore {
+	pub fn open(path: &Path) -> Result<SqliteStore> {
+		if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+			std::fs::create_dir_all(dir).map_err(LgError::io("Log directory could not be created."))?;
+		}
+		let conn = rusqlite::Connection::open(path)?;
+		conn.execute_batch("
+			CREATE TABLE IF NOT EXISTS profiles (
+				name TEXT PRIMARY KEY
+			);
+			CREATE TABLE IF NOT EXISTS entries (
+				profile TEXT NOT NULL REFERENCES profiles(name),
+				utc INTEGER NOT NULL,
+				time TEXT NOT NULL,
+				data TEXT NOT NULL,
+				kind TEXT NOT NULL,
+				note TEXT NOT NULL,
+				PRIMARY KEY (profile, utc)
+			);
+		")?;
+		Ok(SqliteStore { conn })
+	}
+
+	fn entries(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<LogEntry>> {
+		let mut query = self.conn.prepare(sql)?;
+		let rows = query.query_map(params, |row| {
+			let time: String = row.get(0)?;
+			Ok((time, LogEntry {
+				data: row.get(1)?,
+				kind: row.get(2)?,
+				note: row.get(3)?,
+				..LogEntry::default()
+			}))
+		})?;
+		let mut entries = Vec::new();
+		for row in rows {
+			let (time, entry) = row?;
+			let time = DateTime::parse_from_rfc3339(&time)
+				.map_err(|err| LgError::Invalid("Log database holds an invalid timestamp.".to_string(), err.to_string()))?;
+			entries.push(LogEntry { time, ..entry });
+		}
+		Ok(entries)
+	}
+
+	fn insert(tx: &rusqlite::Transaction, profile: &str, entries: &[LogEntry]) -> Result<()> {
+		let mut insert = tx.prepare_cached(
+			"INSERT OR REPLACE INTO entries (profile, utc, time, data, kind, note) VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+		)?;
+		for entry in entries {
+			insert.execute(rusqlite::params![
+				profile, entry.time.timestamp(), entry.time.to_rfc3339(), entry.data, entry.kind, entry.note
+			])?;
+		}
+		Ok(())
+	}
+}
+
+impl LogStore for SqliteStore {
+	fn load(&self) -> Result<HashMap<String, Log>> {
+		let mut query = self.conn.prepare("SELECT name FROM profiles")?;
+		let names = query.query_map([], |row| row.get::<_, String>(0))?
+			.collect::<rusqlite::Result<Vec<_>>>()?;
+		let mut log_set = HashMap::new();
+		for name in names {
+			// Rows come out sorted, so they can go straight into the log.
+			let mut log = Log::new();
+			log.vec = self.entries(
+				"SELECT time, data, kind, note FROM entries WHERE profile = ?1 ORDER BY utc",
+				&[&name])?;
+			log_set.insert(name, log);
+		}
+		Ok(log_set)
+	}
+
+	fn profiles(&self) -> Result<Vec<String>> {
+		let mut query = self.conn.prepare("SELECT name FROM profiles ORDER BY name")?;
+		let names = query.query_map([], |row| row.get::<_, String>(0))?
+			.collect::<rusqlite::Result<Vec<_>>>()?;
+		Ok(names)
+	}
+
+	fn save(&mut self, log_set: &HashMap<String, Log>) -> Result<()> {
+		let tx = self.conn.transaction()?;
+		tx.execute_batch("DELETE FROM entries; DELETE FROM profiles;")?;
+		for (profile, log) in log_set {
+			tx.execute("INSERT INTO profiles (name) VALUES (?1)", [profile])?;
+			SqliteStore::insert(&tx, profile, &log.vec)?;
+		}
+		tx.commit()?;
+		Ok(())
+	}
+
+	fn range(&self, profile: &str, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> Result<Vec<LogEntry>> {
+		// Include the entry already running at `start`, and the next after `end`.
+		self.entries("
+			SELECT time, data, kind, note FROM entries
+			WHERE profile = ?1
+			AND utc >= COALESCE(
+				(SELECT MAX(utc) FROM entries WHERE profile = ?1 AND utc <= ?2), ?2)
+			AND utc <= COALESCE(
+				(SELECT MIN(utc) FROM entries WHERE profile = ?1 AND utc > ?3), ?3)
+			ORDER BY utc",
+			&[&profile, &start.timestamp(), &end.timestamp()])
+	}
+
+	fn apply_updates(&mut self, profile: &str, removed: &[LogEntry], added: &[LogEntry]) -> Result<()> {
+		let tx = self.conn.transaction()?;
+		tx.execute("INSERT OR IGNORE INTO profiles (name) VALUES (?1)", [profile])?;
+		for entry in removed {
+			tx.execute("DELETE FROM entries WHERE profile = ?1 AND utc = ?2", rusqlite::params![profile, entry.time.timestamp()])?;
+		}
+		SqliteStore::insert(&tx, profile, added)?;
+		tx.commit()?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::at;
+
+	fn scratch(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("lg-test-{}-{}", name, std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		std::fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	fn log(entries: &[(&str, &str)]) -> Log {
+		let mut log = Log::new();
+		log.vec = entries.iter()
+			.map(|(time, data)| LogEntry::new(at(time), "Work", data, ""))
+			.collect();
+		log.vec.sort();
+		log
+	}
+
+	fn data(entries: &[LogEntry]) -> Vec<&str> {
+		entries.iter().map(|entry| entry.data.as_str()).collect()
+	}
+
+	fn log_set() -> HashMap<String, Log> {
+		let mut log_set = HashMap::new();
+		log_set.insert("default".to_string(), log(&[("2026-10-18T09:00:00-07:00", "A"), ("2026-10-18T10:00:00-07:00", "B"),
+			("2026-10-18T11:00:00+02:00", "C"), ("2026-10-18T12:00:00-07:00", "D")]));
+		log_set.insert("side".to_string(), log(&[("2026-10-18T09:30:00-07:00", "S")]));
+		log_set.insert("empty".to_string(), Log::new());
+		log_set
+	}
+
+	#[test]
+	fn sqlite_saves_and_loads_every_profile() {
+		let dir = scratch("sqlite-save");
+		let mut store = SqliteStore::open(&dir.join("log.sqlite")).unwrap();
+		store.save(&log_set()).unwrap();
+		let loaded = store.load().unwrap();
+		assert_eq!(store.profiles().unwrap(), vec!["default", "empty", "side"]);
+		for (profile, log) in log_set() {
+			let entries = loaded[&profile].vec.iter().map(|e| (e.time.to_rfc3339(), e.data.as_str(), e.kind.as_str())).collect::<Vec<_>>();
+			assert_eq!(entries, log.vec.iter().map(|e| (e.time.to_rfc3339(), e.data.as_str(), e.kind.as_str())).collect::<Vec<_>>());
+		}
+		// Saving replaces what was there.
+		store.save(&HashMap::new()).unwrap();
+		assert!(store.load().unwrap().is_empty());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn sqlite_ranges_hold_the_running_and_the_next_entry() {
+		let dir = scratch("sqlite-range");
+		let mut store = SqliteStore::open(&dir.join("log.sqlite")).unwrap();
+		store.save(&log_set()).unwrap();
+		let range = |profile, start, end| store.range(profile, at(start), at(end)).unwrap();
+		// C is 02:00 in Los Angeles, before A.
+		assert_eq!(data(&range("default", "2026-10-18T09:30:00-07:00", "2026-10-18T10:30:00-07:00")), vec!["A", "B", "D"]);
+		assert_eq!(data(&range("default", "2026-10-18T10:00:00-07:00", "2026-10-18T10:00:00-07:00")), vec!["B", "D"]);
+		assert_eq!(data(&range("default", "2026-10-18T01:00:00-07:00", "2026-10-18T01:30:00-07:00")), vec!["C"]);
+		assert_eq!(data(&range("default", "2026-10-18T13:00:00-07:00", "2026-10-18T14:00:00-07:00")), vec!["D"]);
+		assert!(range("empty", "2026-10-18T09:00:00-07:00", "2026-10-18T10:00:00-07:00").is_empty());
+		assert!(range("nobody", "2026-10-18T09:00:00-07:00", "2026-10-18T10:00:00-07:00").is_empty());
+		// The same as the YAML store gives.
+		let yaml_path = dir.join("log.yml");
+		let mut yaml = open(&yaml_path, 0).unwrap();
+		yaml.save(&log_set()).unwrap();
+		let (start, end) = (at("2026-10-18T08:00:00-07:00"), at("2026-10-18T11:00:00-07:00"));
+		assert_eq!(data(&yaml.range("default", start, end).unwrap()), data(&store.range("default", start, end).unwrap()));
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn sqlite_applies_updates_to_one_profile() {
+		let dir = scratch("sqlite-updates");
+		let mut store = SqliteStore::open(&dir.join("log.sqlite")).unwrap();
+		store.save(&log_set()).unwrap();
+		let removed = log(&[("2026-10-18T10:00:00-07:00", "B")]).vec;
+		let added = log(&[("2026-10-18T10:15:00-07:00", "b")]).vec;
+		store.apply_updates("default", &removed, &added).unwrap();
+		store.apply_updates("new", &[], &added).unwrap();
+		let loaded = store.load().unwrap();
+		assert_eq!(data(&loaded["default"].vec), vec!["C", "A", "b", "D"]);
+		assert_eq!(data(&loaded["side"].vec), vec!["S"]);
+		assert_eq!(data(&loaded["new"].vec), vec!["b"]);
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn copying_keeps_every_profile() {
+		let dir = scratch("copy");
+		open(&dir.join("log.yml"), 0).unwrap().save(&log_set()).unwrap();
+		copy(&dir.join("log.yml"), &dir.join("log.sqlite"), 0).unwrap();
+		copy(&dir.join("log.sqlite"), &dir.join("back.yml"), 0).unwrap();
+		for path in ["log.sqlite", "back.yml"] {
+			let loaded = open(&dir.join(path), 0).unwrap().load().unwrap();
+			let mut profiles = loaded.keys().map(String::as_str).collect::<Vec<_>>();
+			profiles.sort();
+			assert_eq!(profiles, vec!["default", "empty", "side"]);
+			for (profile, log) in log_set() {
+				assert_eq!(data(&loaded[&profile].vec), data(&log.vec), "{} {}", path, profile);
+			}
+		}
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
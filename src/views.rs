@@ -0,0 +1,248 @@
+// //// Views //// //
+use chrono::prelude::*;
+use chrono::Duration;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use super::error::{LgError, Result};
+use super::log::Log;
+use super::time;
+
+// How wide the timelines are drawn: `--width`, else the terminal's, else
+// `COLUMNS`, else 80. And the rows of the bare `lg` view: an hour each
+// unless the config's `period` or `--period` says otherwise.
+static WIDTH: AtomicUsize = AtomicUsize::new(80);
+static PERIOD: RwLock<Option<Duration>> = RwLock::new(None);
+
+pub fn set_width(width: Option<usize>) {
+	let width = width
+		.or_else(|| terminal_size::terminal_size().map(|(width, _)| usize::from(width.0)))
+		.or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+		.unwrap_or(80);
+	WIDTH.store(width, Ordering::Relaxed);
+}
+
+pub fn width() -> usize {
+	WIDTH.load(Ordering::Relaxed)
+}
+
+pub fn set_period(period: &str) -> Result<()> {
+	// Rows that divide a day evenly, so each starts at the same times daily.
+	let parsed = time::parse_duration(period).ok()
+		.filter(|period| [15, 30, 60, 120, 24 * 60].contains(&period.num_minutes()) & (period.num_seconds() % 60 == 0))
+		.ok_or_else(|| LgError::Invalid(format!("Row period \"{}\" is invalid.", period), "Must be one of 15m, 30m, 1h, 2h or 1d.".to_string()))?;
+	*PERIOD.write().unwrap() = Some(parsed);
+	Ok(())
+}
+
+pub fn period() -> Duration {
+	PERIOD.read().unwrap().unwrap_or_else(|| Duration::hours(1))
+}
+
+pub fn parse_range(range: Option<&str>, now: DateTime<FixedOffset>, window: &time::Window) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+	// The span of `lg report` and `lg list`: dates as for `lg day`,
+	// failing which times as for `lg hr`; today by default.
+	let range = range.unwrap_or("today");
+	Span::Day.range(Some(range), now, window)
+		.or_else(|_| Span::Hour.range(Some(range), now, window))
+}
+
+// The timelines of `lg hr`, `lg day` and `lg week`: one row per hour,
+// day or week.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Span {
+	Hour,
+	Day,
+	Week,
+}
+
+impl Span {
+	pub fn parse(name: &str) -> Option<Span> {
+		match name {
+			"hr" | "hour" => Some(Span::Hour),
+			"day" => Some(Span::Day),
+			"week" => Some(Span::Week),
+			_ => None,
+		}
+	}
+
+	pub fn period(self) -> Duration {
+		match self {
+			Span::Hour => Duration::hours(1),
+			Span::Day => Duration::days(1),
+			Span::Week => Duration::weeks(1),
+		}
+	}
+
+	fn count(self) -> i32 {
+		// Rows shown when no range is given: a day, four weeks, six months.
+		match self {
+			Span::Hour => 24,
+			Span::Day => 28,
+			Span::Week => 24,
+		}
+	}
+
+	pub fn floor(self, time: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+		// The start of the hour, day, or week from Monday holding `time`,
+		// days starting at local midnight.
+		let date = time::to_local(time).date_naive();
+		match self {
+			Span::Hour => time - Duration::seconds(i64::from(time.minute() * 60 + time.second()))
+				- Duration::nanoseconds(i64::from(time.nanosecond())),
+			Span::Day => time::local_datetime(date.and_time(NaiveTime::MIN)),
+			Span::Week => time::local_datetime((date - Duration::days(i64::from(date.weekday().num_days_from_monday())))
+				.and_time(NaiveTime::MIN)),
+		}
+	}
+
+	pub fn range(self, range: Option<&str>, now: DateTime<FixedOffset>, window: &time::Window) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+		// The latest `24` rows through now, one date or `Feb4-Feb10` for
+		// days and weeks, or one time or `14:37-15:00` for hours, the
+		// first of them read as `@` reads it in `window`.
+		let invalid = || LgError::InvalidRange(range.unwrap_or_default().to_string());
+		let range = match range {
+			Some(range) => range,
+			None => return Ok(self.latest(self.count(), now)),
+		};
+		if let Ok(count) = range.parse::<i32>() {
+			return match count {
+				1..=9999 => Ok(self.latest(count, now)),
+				_ => Err(invalid()),
+			};
+		}
+		// Dates hold dashes too, as in `2019-02-04-2019-02-10`; split at
+		// the first dash leaving a valid range on both sides, failing
+		// which it's one date or time, as `2019-02-04`.
+		let splits = range.match_indices('-')
+			.filter(|(i, _)| *i > 0)
+			.map(|(i, _)| (&range[..i], Some(&range[i + 1..])));
+		for (from, till) in splits.chain(Some((range, None))) {
+			let parsed = match self {
+				Span::Hour => clock_span(from, till, now, window),
+				Span::Day | Span::Week => self.date_span(from, till, now.date_naive()),
+			};
+			if let Some((start, end)) = parsed.filter(|(start, end)| start < end) {
+				return Ok((start, end));
+			}
+		}
+		Err(invalid())
+	}
+
+	fn shift(self, row: DateTime<FixedOffset>, count: i32) -> DateTime<FixedOffset> {
+		// `count` rows on from `row`: hours by the clock's hands, days and
+		// weeks by the calendar, to the local midnight starting that row.
+		match self {
+			Span::Hour => row + self.period() * count,
+			Span::Day | Span::Week => {
+				let date = time::to_local(row).date_naive() + self.period() * count;
+				self.floor(time::local_datetime(date.and_time(NaiveTime::MIN)))
+			},
+		}
+	}
+
+	fn latest(self, count: i32, now: DateTime<FixedOffset>) -> (DateTime<FixedOffset>, DateTime<FixedOffset>) {
+		let end = self.shift(self.floor(now), 1);
+		(self.shift(end, -count), end)
+	}
+
+	fn date_span(self, from: &str, till: Option<&str>, today: NaiveDate) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+		// The rows from `from`'s through `till`'s, inclusive.
+		let from = parse_day(from, today)?;
+		let till = match till {
+			Some(till) => parse_day(till, today)?,
+			None => from,
+		};
+		let start = self.floor(time::local_datetime(from.and_time(NaiveTime::MIN)));
+		let end = self.shift(self.floor(time::local_datetime(till.and_time(NaiveTime::MIN))), 1);
+		Some((start, end))
+	}
+
+	pub fn draw(self, log: &Log, start: DateTime<FixedOffset>, end: DateTime<FixedOffset>) -> String {
+		// Rows start on whole periods, blank before `start`.
+		let mut slice = log.slice(start, end);
+		slice.start_bound = self.floor(start);
+		slice.draw(width(), self.period())
+	}
+}
+
+fn parse_day(day_str: &str, today: NaiveDate) -> Option<NaiveDate> {
+	// Any date `@` takes, or a bare day of the month (the latest such).
+	match day_str.parse::<u32>() {
+		Ok(day) => match today.with_day(day) {
+			Some(date) if date <= today => Some(date),
+			_ => (today.with_day(1)? - Duration::days(1)).with_day(day),
+		},
+		Err(_) => time::parse_date(day_str, today),
+	}
+}
+
+fn clock_span(from: &str, till: Option<&str>, now: DateTime<FixedOffset>, window: &time::Window) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+	// `14:37-15:00`, `14-16` for whole hours, or `:37-:00` for minutes of
+	// the hour: `from` as `@` reads it through the first `till` after it.
+	// One time alone is the hour holding it.
+	let start = match minute_of_hour(from) {
+		Some(minute) => {
+			let start = Span::Hour.floor(now) + minute;
+			if start > now { start - Duration::hours(1) } else { start }
+		},
+		None => time::parse_start(&whole_hour(from), now, window).ok()?,
+	};
+	let end = match till {
+		None => return Some((Span::Hour.floor(start), Span::Hour.floor(start) + Duration::hours(1))),
+		Some(till) => match minute_of_hour(till) {
+			Some(minute) => {
+				let end = Span::Hour.floor(start) + minute;
+				if end <= start { end + Duration::hours(1) } else { end }
+			},
+			None => time::parse_datetime(&whole_hour(till), start, now).ok()?,
+		},
+	};
+	Some((start, end))
+}
+
+fn minute_of_hour(time_str: &str) -> Option<Duration> {
+	let minute = time_str.strip_prefix(':')?.parse::<i64>().ok()?;
+	(0..60).contains(&minute).then(|| Duration::minutes(minute))
+}
+
+fn whole_hour(time_str: &str) -> String {
+	// `14` is `14:00`.
+	match time_str.parse::<u32>() {
+		Ok(hour) => format!("{}:00", hour),
+		Err(_) => time_str.to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::at;
+	use crate::time::{Clock, FixedClock, Window};
+
+	#[test]
+	fn hr_ranges_are_read_from_the_clock() {
+		let clock = FixedClock(at("2026-10-18T09:30:00-07:00"));
+		let range = |range| Span::Hour.range(range, clock.now(), &Window::default()).unwrap();
+		assert_eq!(range(None), (at("2026-10-17T10:00:00-07:00"), at("2026-10-18T10:00:00-07:00")));
+		assert_eq!(range(Some("3")), (at("2026-10-18T07:00:00-07:00"), at("2026-10-18T10:00:00-07:00")));
+		assert_eq!(range(Some("22-23")), (at("2026-10-17T22:00:00-07:00"), at("2026-10-17T23:00:00-07:00")));
+		assert_eq!(range(Some("8:15-9:00")), (at("2026-10-18T08:15:00-07:00"), at("2026-10-18T09:00:00-07:00")));
+		assert_eq!(range(Some(":37-:00")), (at("2026-10-18T08:37:00-07:00"), at("2026-10-18T09:00:00-07:00")));
+		assert_eq!(range(Some("9:10")), (at("2026-10-18T09:00:00-07:00"), at("2026-10-18T10:00:00-07:00")));
+	}
+
+	#[test]
+	fn day_rows_start_at_local_midnights() {
+		// 2026-11-01 is 25 hours long; the rows still end with 11-03.
+		let now = at("2026-11-03T12:00:00-08:00");
+		let (start, end) = Span::Day.range(Some("oct31-nov3"), now, &Window::default()).unwrap();
+		assert_eq!((start, end), (at("2026-10-31T00:00:00-07:00"), at("2026-11-04T00:00:00-08:00")));
+		assert_eq!(Span::Day.latest(2, now), (at("2026-11-02T00:00:00-08:00"), end));
+		let labels = Span::Day.draw(&Log::new(), start, end).lines()
+			.filter_map(|line| line.split('▏').next())
+			.filter(|label| label.contains('-'))
+			.map(str::to_string)
+			.collect::<Vec<_>>();
+		assert_eq!(labels, vec!["Sat 10-31", "Sun 11-01", "Mon 11-02", "Tue 11-03"]);
+	}
+}